@@ -1,23 +1,127 @@
-use anyhow::Result;
-use chrono::{DateTime, Utc};
-use dateparser::DateTimeUtc;
+use anyhow::{anyhow, Result};
+use chrono::{
+    format::{parse_and_remainder, Item, Parsed, StrftimeItems},
+    DateTime, FixedOffset, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use clap::ValueEnum;
 use icu::collator::Collator;
-use ipnet::IpNet;
+use idna::{domain_to_ascii_cow, AsciiDenyList};
+use ipnet::{IpNet, Ipv4Subnets, Ipv6Subnets};
 use lazy_regex::regex;
 use log::debug;
+use std::borrow::Cow;
 use std::cmp::Ordering;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
 use typed_path::{
-    Utf8Component, Utf8Encoding, Utf8Path, Utf8UnixPath, Utf8WindowsComponent, Utf8WindowsPath,
+    Utf8Component, Utf8Encoding, Utf8Path, Utf8PathBuf, Utf8UnixPath, Utf8WindowsComponent,
+    Utf8WindowsPath,
 };
+use url::{Host, Url};
 
 pub(crate) trait Comparer {
+    // Returns true if `str1` must come after `str2`. Equal lines can be in
+    // either order, whether or not the lines are reversed.
     fn is_ordered(&self, str1: &str, str2: &str, reverse: bool) -> Result<bool> {
         let ord = self.cmp(str1, str2)?;
-        Ok(if reverse { !ord.is_gt() } else { ord.is_gt() })
+        Ok(if reverse { ord.is_lt() } else { ord.is_gt() })
     }
 
     fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering>;
+
+    // Returns an error if `cmp` cannot handle the given line. Most comparers
+    // fall back to comparing lines as text, so they can handle any line.
+    fn check_line(&self, _str: &str) -> Result<()> {
+        Ok(())
+    }
+
+    // Returns an error if the line does not match what this comparer
+    // expects, even if `cmp` can handle it. This is stricter than
+    // `check_line`.
+    fn validate_line(&self, str: &str) -> Result<()> {
+        self.check_line(str)
+    }
+
+    // Returns the canonical form of the line. Comparers which do not parse
+    // lines into anything with a canonical form return the line unchanged.
+    fn canonicalize(&self, str: &str) -> Result<String> {
+        Ok(str.to_string())
+    }
+
+    // Returns the value used to decide whether two lines are duplicates. For
+    // most comparers this is the line itself.
+    fn unique_key(&self, str: &str) -> Result<String> {
+        Ok(str.to_string())
+    }
+}
+
+// This wraps a comparer that cannot handle some lines. Lines which the inner
+// comparer can't handle are sorted as text, either before or after all of the
+// lines that it can handle.
+pub(crate) struct InvalidLinesComparer {
+    inner: Box<dyn Comparer + Sync>,
+    invalid_first: bool,
+}
+
+impl Comparer for InvalidLinesComparer {
+    fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
+        let valid1 = self.inner.check_line(str1).is_ok();
+        let valid2 = self.inner.check_line(str2).is_ok();
+
+        match (valid1, valid2) {
+            (true, true) => self.inner.cmp(str1, str2),
+            (false, false) => {
+                debug!("  Neither side is valid, comparing the values as strings");
+                Ok(str1.cmp(str2))
+            }
+            (true, false) => {
+                debug!("  Only the left side is valid");
+                Ok(if self.invalid_first {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                })
+            }
+            (false, true) => {
+                debug!("  Only the right side is valid");
+                Ok(if self.invalid_first {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                })
+            }
+        }
+    }
+
+    fn validate_line(&self, str: &str) -> Result<()> {
+        self.inner.validate_line(str)
+    }
+
+    // Invalid lines are left as-is, since they are sorted as text.
+    fn canonicalize(&self, str: &str) -> Result<String> {
+        if self.inner.check_line(str).is_err() {
+            return Ok(str.to_string());
+        }
+        self.inner.canonicalize(str)
+    }
+
+    fn unique_key(&self, str: &str) -> Result<String> {
+        if self.inner.check_line(str).is_err() {
+            return Ok(str.to_string());
+        }
+        self.inner.unique_key(str)
+    }
+}
+
+impl InvalidLinesComparer {
+    pub(crate) fn new(inner: Box<dyn Comparer + Sync>, invalid_first: bool) -> Self {
+        Self {
+            inner,
+            invalid_first,
+        }
+    }
 }
 
 pub(crate) struct TextComparer {
@@ -148,6 +252,14 @@ impl Comparer for NumberedTextComparer {
             }
         }
     }
+
+    fn validate_line(&self, str: &str) -> Result<()> {
+        if Self::starts_with_number(str) {
+            Ok(())
+        } else {
+            Err(anyhow!("the line does not start with a number"))
+        }
+    }
 }
 
 impl NumberedTextComparer {
@@ -157,24 +269,47 @@ impl NumberedTextComparer {
             case_insensitive,
         }
     }
+
+    pub(crate) fn starts_with_number(str: &str) -> bool {
+        regex!(r"\A[0-9]+(?:\.[0-9]+)?").is_match(str)
+    }
 }
 
 pub(crate) struct DatetimeTextComparer {
     collator: Option<Collator>,
     case_insensitive: bool,
+    // When this is empty, datetimes are parsed by `dateparser` instead.
+    formats: Vec<DatetimeFormat>,
+    timezone: Timezone,
+    date_order: DateOrder,
+}
+
+struct DatetimeFormat {
+    format: String,
+    items: Vec<Item<'static>>,
 }
 
 impl Comparer for DatetimeTextComparer {
     fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
         debug!("DatetimeTextComparer comparing `{str1}` <=> `{str2}`");
 
-        let dt1 = Self::datetime_from_str(str1);
-        let dt2 = Self::datetime_from_str(str2);
+        let dt1 = self.parse_datetime(str1);
+        let dt2 = self.parse_datetime(str2);
 
         match (dt1, dt2) {
             (Some(dt1), Some(dt2)) => {
                 debug!("Both strings match the datetime regex: `{dt1}` <=> `{dt2}`");
-                Ok(dt1.cmp(&dt2))
+                let ord = dt1.cmp(&dt2);
+                if ord != Ordering::Equal {
+                    return Ok(ord);
+                }
+                debug!("  The datetimes are equal so the comparison will look at the whole of each string");
+                Ok(compare_two_strings(
+                    self.collator.as_ref(),
+                    self.case_insensitive,
+                    str1,
+                    str2,
+                ))
             }
             (Some(_), None) => {
                 debug!("  Only the left side has a valid datetime ");
@@ -195,55 +330,391 @@ impl Comparer for DatetimeTextComparer {
             }
         }
     }
+
+    fn validate_line(&self, str: &str) -> Result<()> {
+        if self.parse_datetime(str).is_some() {
+            Ok(())
+        } else if self.formats.is_empty() {
+            Err(anyhow!(
+                "the line does not start with a datetime or have one in square brackets"
+            ))
+        } else {
+            Err(anyhow!(
+                "the line does not start with a datetime in any of these formats: {}",
+                self.formats
+                    .iter()
+                    .map(|f| format!("`{}`", f.format))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ))
+        }
+    }
 }
 
 impl DatetimeTextComparer {
-    pub(crate) fn new(collator: Option<Collator>, case_insensitive: bool) -> Self {
-        Self {
+    pub(crate) fn new(
+        collator: Option<Collator>,
+        case_insensitive: bool,
+        formats: &[String],
+        timezone: Timezone,
+        date_order: DateOrder,
+    ) -> Result<Self> {
+        let formats = formats
+            .iter()
+            .map(|f| {
+                let items = StrftimeItems::new(f)
+                    .parse_to_owned()
+                    .map_err(|e| anyhow!("the datetime format `{f}` is not valid: {e}"))?;
+                Ok(DatetimeFormat {
+                    format: f.clone(),
+                    items,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
             collator,
             case_insensitive,
+            formats,
+            timezone,
+            date_order,
+        })
+    }
+
+    fn parse_datetime(&self, str: &str) -> Option<DateTime<Utc>> {
+        if self.formats.is_empty() {
+            return Self::datetime_from_str_with(str, self.timezone, self.date_order);
         }
+        self.formats
+            .iter()
+            .find_map(|f| self.datetime_from_str_with_format(str, f))
     }
 
-    fn datetime_from_str(str: &str) -> Option<DateTime<Utc>> {
-        let datetime_text_re = regex!(
-            r#"(?x)
-            \A
-            (?P<datetime>\d\S+)
-            (?:\s*|\z)
-            \z
-        "#
-        );
-        if let Some(caps) = datetime_text_re.captures(str) {
-            if let Some(dt_text) = caps.name("datetime") {
-                if let Ok(dt) = dt_text.as_str().parse::<DateTimeUtc>() {
-                    return Some(dt.0);
-                }
-            }
+    // The format has to match the start of the line, and it must be followed
+    // by whitespace or the end of the line. A format without a time gives
+    // midnight, and a format without an offset is in the comparer's timezone.
+    fn datetime_from_str_with_format(
+        &self,
+        str: &str,
+        format: &DatetimeFormat,
+    ) -> Option<DateTime<Utc>> {
+        let mut parsed = Parsed::new();
+        let rest = parse_and_remainder(&mut parsed, str, format.items.iter()).ok()?;
+        if !(rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+            debug!(
+                "  `{str}` matches the datetime format `{}` but is followed by `{rest}`",
+                format.format,
+            );
+            return None;
+        }
+
+        if let Ok(dt) = parsed.to_datetime() {
+            return Some(dt.to_utc());
+        }
+        // A Unix timestamp is always in UTC.
+        if parsed.timestamp().is_some() {
+            return parsed
+                .to_naive_datetime_with_offset(0)
+                .ok()
+                .map(|dt| dt.and_utc());
+        }
+        let local = parsed.to_naive_datetime_with_offset(0).ok().or_else(|| {
+            parsed
+                .to_naive_date()
+                .ok()
+                .map(|d| d.and_time(NaiveTime::MIN))
+        })?;
+        self.timezone.to_utc(&local)
+    }
+
+    pub(crate) fn datetime_from_str(str: &str) -> Option<DateTime<Utc>> {
+        Self::datetime_from_str_with(str, Timezone::default(), DateOrder::default())
+    }
+
+    // This looks for a datetime at the start of the line first, trying the
+    // longest layout that matches. If there isn't one, it looks at the first
+    // text in square brackets, which is where the datetime is in a Common Log
+    // Format line like `127.0.0.1 - - [17/Oct/2026:12:00:01 +0000] "GET /"`.
+    fn datetime_from_str_with(
+        str: &str,
+        timezone: Timezone,
+        date_order: DateOrder,
+    ) -> Option<DateTime<Utc>> {
+        let leading_res = [
+            // A date followed by a time, with an optional AM/PM and offset,
+            // like `2026-10-17 12:00:01 +0000` or `10/17/2026 12:00 PM`.
+            regex!(
+                r"(?x)
+                \A
+                (?P<datetime>
+                    \d\S*
+                    \s+
+                    \d{1,2}:\d{2}(?::\d{2})?(?:\.\d{1,9})?
+                    (?:\s*[AaPp][Mm])?
+                    (?:\s*(?:UTC|GMT|[+-]\d{2}:?\d{2}))?
+                )
+                (?:\s|\z)
+                "
+            ),
+            // A syslog timestamp, like `Oct 17 12:00:01`. This doesn't
+            // include the year, so `dateparser` uses the current year.
+            regex!(
+                r"(?x)
+                \A
+                (?P<datetime>[A-Z][a-z]{2}\s+\d{1,2}\s+\d{1,2}:\d{2}:\d{2})
+                (?:\s|\z)
+                "
+            ),
+            // A datetime without any spaces, like `2026-10-17T12:00:01Z`.
+            regex!(
+                r"(?x)
+                \A
+                (?P<datetime>\d\S+)
+                (?:\s|\z)
+                "
+            ),
+            regex!(r"\[(?P<datetime>[^\]]+)\]"),
+        ];
+        leading_res.iter().find_map(|re| {
+            let dt_text = re.captures(str)?.name("datetime")?.as_str();
+            debug!("  trying to parse `{dt_text}` as a datetime");
+            Self::parse_datetime_text(dt_text, timezone, date_order)
+        })
+    }
+
+    fn parse_datetime_text(
+        str: &str,
+        timezone: Timezone,
+        date_order: DateOrder,
+    ) -> Option<DateTime<Utc>> {
+        // This is the Common Log Format layout, which `dateparser` doesn't
+        // handle.
+        if let Ok(dt) = DateTime::parse_from_str(str, "%d/%b/%Y:%H:%M:%S %z") {
+            return Some(dt.to_utc());
+        }
+        // `dateparser` only accepts a year-first date with two digit months
+        // and days, like `2026-01-02`, so `2026-1-2` is padded to match.
+        let str = match regex!(r"\A([0-9]{4})-([0-9]{1,2})-([0-9]{1,2})\b").captures(str) {
+            Some(caps) if caps[2].len() == 1 || caps[3].len() == 1 => Cow::Owned(format!(
+                "{}-{:0>2}-{:0>2}{}",
+                &caps[1],
+                &caps[2],
+                &caps[3],
+                &str[caps[0].len()..],
+            )),
+            _ => Cow::Borrowed(str),
+        };
+        timezone.parse(&date_order.reorder(&str))
+    }
+}
+
+// This is the timezone for datetimes that don't include an offset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Timezone {
+    Named(Tz),
+    Offset(FixedOffset),
+}
+
+impl Default for Timezone {
+    fn default() -> Self {
+        Timezone::Named(Tz::UTC)
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(tz) = s.parse::<Tz>() {
+            return Ok(Timezone::Named(tz));
         }
+        if let Ok(offset) = s.parse::<FixedOffset>() {
+            return Ok(Timezone::Offset(offset));
+        }
+        Err(anyhow!(
+            "`{s}` is not an IANA timezone name like `Europe/Berlin` or a UTC offset like `+05:30`",
+        ))
+    }
+}
 
-        None
+impl Timezone {
+    // If a local time happens twice because the clocks go back, this picks
+    // the earlier of the two. A local time that is skipped when the clocks
+    // go forward does not exist, so this returns `None`.
+    fn to_utc(self, local: &NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Timezone::Named(tz) => tz
+                .from_local_datetime(local)
+                .earliest()
+                .map(|dt| dt.to_utc()),
+            Timezone::Offset(offset) => offset
+                .from_local_datetime(local)
+                .earliest()
+                .map(|dt| dt.to_utc()),
+        }
+    }
+
+    // Dates without a time are parsed as midnight.
+    fn parse(self, str: &str) -> Option<DateTime<Utc>> {
+        match self {
+            Timezone::Named(tz) => dateparser::parse_with(str, &tz, NaiveTime::MIN),
+            Timezone::Offset(offset) => dateparser::parse_with(str, &offset, NaiveTime::MIN),
+        }
+        .ok()
     }
 }
 
-#[derive(PartialEq)]
+// This determines how a numeric date like `01/02/2026` is parsed. By default,
+// this is left to `dateparser`, which parses dates with slashes as month
+// first.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum DateOrder {
+    #[default]
+    Auto,
+    DayFirst,
+    MonthFirst,
+}
+
+impl DateOrder {
+    // Numeric dates are rewritten as `month/day/year`, which is the only
+    // order `dateparser` accepts for them. The separator can be `/`, `.`, or
+    // `-`.
+    fn reorder(self, str: &str) -> Cow<'_, str> {
+        if self == DateOrder::Auto {
+            return Cow::Borrowed(str);
+        }
+        let Some(caps) = regex!(
+            r"(?x)
+            \A
+            (?P<first>[0-9]{1,2})
+            (?P<sep1>[/.-])
+            (?P<second>[0-9]{1,2})
+            (?P<sep2>[/.-])
+            (?P<year>[0-9]{4}|[0-9]{2})
+            (?P<rest>\z|\s.*)
+            "
+        )
+        .captures(str)
+        .filter(|c| c["sep1"] == c["sep2"]) else {
+            return Cow::Borrowed(str);
+        };
+        let (month, day) = if self == DateOrder::DayFirst {
+            (&caps["second"], &caps["first"])
+        } else {
+            (&caps["first"], &caps["second"])
+        };
+        Cow::Owned(format!("{month}/{day}/{}{}", &caps["year"], &caps["rest"]))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
 pub(crate) enum PathType {
+    /// Parse every path as a Unix path.
+    #[default]
     Unix,
+    /// Parse every path as a Windows path.
     Windows,
+    /// Decide whether each path is a Windows or Unix path separately.
+    Mixed,
 }
 
+impl PathType {
+    // For mixed paths, a path is a Windows path if it starts with a Windows
+    // prefix, like `C:` or `\\server\share`, or if it contains a backslash but
+    // no forward slash. Any other path is a Unix path.
+    pub(crate) fn for_line(self, str: &str) -> PathType {
+        match self {
+            PathType::Mixed => {
+                if has_windows_prefix(str) || (str.contains('\\') && !str.contains('/')) {
+                    PathType::Windows
+                } else {
+                    PathType::Unix
+                }
+            }
+            t => t,
+        }
+    }
+}
+
+fn has_windows_prefix(str: &str) -> bool {
+    matches!(
+        Utf8WindowsPath::new(str).components().next(),
+        Some(Utf8WindowsComponent::Prefix(_)),
+    )
+}
+
+// This determines which parts of a path are compared first.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub(crate) enum PathOrder {
+    /// Sort paths by depth before sorting by the path content, so /z comes
+    /// before /a/a.
+    #[default]
+    Depth,
+    /// Sort paths in tree order, like the output of find, so each directory
+    /// is followed by everything under it and /a/a comes before /z.
+    Tree,
+    /// Sort paths by their file extension, then sort paths with the same
+    /// extension by depth.
+    Extension,
+    /// Sort paths by their last component, then sort paths with the same
+    /// last component by depth, which orders them by their parent directory.
+    Basename,
+}
+
+// The options for `PathComparer` which control how paths are parsed and
+// ordered.
+#[derive(Clone, Copy, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct PathOptions {
+    pub(crate) path_type: PathType,
+    pub(crate) order: PathOrder,
+    pub(crate) dirs_first: bool,
+    pub(crate) natural: bool,
+    pub(crate) normalize: bool,
+}
+
+#[allow(clippy::struct_excessive_bools)]
 pub(crate) struct PathComparer {
     collator: Option<Collator>,
     case_insensitive: bool,
     path_type: PathType,
+    order: PathOrder,
+    dirs_first: bool,
+    natural: bool,
+    normalize: bool,
 }
 
 impl Comparer for PathComparer {
     fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
-        Ok(match self.path_type {
-            PathType::Unix => self.cmp_unix(str1, str2),
-            PathType::Windows => self.cmp_windows(str1, str2),
-        })
+        if !self.normalize {
+            return Ok(self.cmp_paths(str1, str2));
+        }
+
+        let normal1 = self.canonical_form(str1);
+        let normal2 = self.canonical_form(str2);
+        // Paths which are the same once they're normalized are sorted by
+        // their unique key, so that lines which `--unique` treats as the same
+        // path end up next to each other, and then as text so that their
+        // order is stable.
+        Ok(self
+            .cmp_paths(&normal1, &normal2)
+            .then_with(|| {
+                self.path_key(str1, normal1)
+                    .cmp(&self.path_key(str2, normal2))
+            })
+            .then_with(|| str1.cmp(str2)))
+    }
+
+    fn canonicalize(&self, str: &str) -> Result<String> {
+        Ok(self.canonical_form(str))
+    }
+
+    // On Windows paths are case-insensitive, so two paths that only differ
+    // in case are the same path.
+    fn unique_key(&self, str: &str) -> Result<String> {
+        if !self.normalize {
+            return Ok(str.to_string());
+        }
+        Ok(self.path_key(str, self.canonical_form(str)))
     }
 }
 
@@ -251,13 +722,164 @@ impl PathComparer {
     pub(crate) fn new(
         collator: Option<Collator>,
         case_insensitive: bool,
-        path_type: PathType,
+        opts: PathOptions,
     ) -> Self {
         Self {
             collator,
             case_insensitive,
-            path_type,
+            path_type: opts.path_type,
+            order: opts.order,
+            dirs_first: opts.dirs_first,
+            natural: opts.natural,
+            normalize: opts.normalize,
+        }
+    }
+
+    fn cmp_paths(&self, str1: &str, str2: &str) -> Ordering {
+        match self.path_type {
+            PathType::Unix => self.cmp_unix(str1, str2),
+            PathType::Windows => self.cmp_windows(str1, str2),
+            PathType::Mixed => self.cmp_mixed(str1, str2),
+        }
+    }
+
+    fn canonical_form(&self, str: &str) -> String {
+        let (mut canonical, separator) = match self.path_type.for_line(str) {
+            PathType::Windows => (
+                Self::canonical_path(Utf8WindowsPath::new(str), self.normalize),
+                '\\',
+            ),
+            _ => (
+                Self::canonical_path(Utf8UnixPath::new(str), self.normalize),
+                '/',
+            ),
+        };
+        // When sorting directories first, a trailing separator is what marks
+        // a path as a directory, so we need to keep it.
+        if self.dirs_first
+            && self.has_trailing_separator(str)
+            && canonical != "."
+            && !canonical.ends_with(separator)
+        {
+            canonical.push(separator);
+        }
+        canonical
+    }
+
+    // The unique key for a path is its normalized form, lowercased for
+    // Windows paths.
+    fn path_key(&self, str: &str, normal: String) -> String {
+        match self.path_type.for_line(str) {
+            PathType::Windows => normal.to_lowercase(),
+            _ => normal,
+        }
+    }
+
+    fn cmp_component(&self, str1: &str, str2: &str) -> Ordering {
+        if self.natural {
+            compare_two_strings_naturally(self.collator.as_ref(), self.case_insensitive, str1, str2)
+        } else {
+            compare_two_strings(self.collator.as_ref(), self.case_insensitive, str1, str2)
+        }
+    }
+
+    // A path with a trailing separator is a directory.
+    fn has_trailing_separator(&self, str: &str) -> bool {
+        match self.path_type {
+            PathType::Unix => str.ends_with('/'),
+            PathType::Windows | PathType::Mixed => str.ends_with(['/', '\\']),
+        }
+    }
+
+    // Returns true if `ancestor` is a path to a directory that contains
+    // `path`, looking only at the path components.
+    pub(crate) fn is_ancestor(&self, ancestor: &str, path: &str) -> bool {
+        match self.path_type {
+            PathType::Unix => {
+                self.has_prefix_components(Utf8UnixPath::new(ancestor), Utf8UnixPath::new(path))
+            }
+            PathType::Windows => self
+                .has_prefix_components(Utf8WindowsPath::new(ancestor), Utf8WindowsPath::new(path)),
+            PathType::Mixed => match (has_windows_prefix(ancestor), has_windows_prefix(path)) {
+                (true, true) => self.has_prefix_components(
+                    Utf8WindowsPath::new(ancestor),
+                    Utf8WindowsPath::new(path),
+                ),
+                (false, false) => self.has_prefix_components(
+                    Utf8UnixPath::new(&Self::as_unix_path(ancestor)),
+                    Utf8UnixPath::new(&Self::as_unix_path(path)),
+                ),
+                _ => false,
+            },
+        }
+    }
+
+    // Windows paths without a prefix are turned into Unix paths so they can
+    // be compared with Unix paths.
+    fn as_unix_path(str: &str) -> Cow<'_, str> {
+        if PathType::Mixed.for_line(str) == PathType::Windows {
+            Cow::Owned(str.replace('\\', "/"))
+        } else {
+            Cow::Borrowed(str)
+        }
+    }
+
+    fn has_prefix_components<T>(&self, ancestor: &Utf8Path<T>, path: &Utf8Path<T>) -> bool
+    where
+        T: for<'enc> Utf8Encoding<'enc>,
+    {
+        let ancestor_elems = ancestor.components().collect::<Vec<_>>();
+        let path_elems = path.components().collect::<Vec<_>>();
+        if ancestor_elems.len() >= path_elems.len() {
+            return false;
+        }
+
+        ancestor_elems.iter().zip(path_elems.iter()).all(|(a, p)| {
+            compare_two_strings(None, self.case_insensitive, a.as_str(), p.as_str())
+                == Ordering::Equal
+        })
+    }
+
+    // Any string is a valid path, so this just looks for a path separator.
+    pub(crate) fn looks_like_path(str: &str) -> bool {
+        str.contains('/') || str.contains('\\')
+    }
+
+    // This rebuilds the path from its components, which removes repeated and
+    // trailing separators as well as any `.` components. On Windows this also
+    // turns every separator into a backslash. By default it does not touch
+    // `..` components, since removing those could change which file the path
+    // refers to if the directory before it is a symlink. If
+    // `resolve_parents` is true, each `..` removes the component before it
+    // instead, and a `..` right after the root is dropped. A `..` at the start
+    // of a relative path is always kept.
+    fn canonical_path<T>(path: &Utf8Path<T>, resolve_parents: bool) -> String
+    where
+        T: for<'enc> Utf8Encoding<'enc>,
+    {
+        let mut components = vec![];
+        for c in path.components().filter(|c| !c.is_current()) {
+            if resolve_parents && c.is_parent() {
+                match components.last() {
+                    Some(last) if Utf8Component::is_normal(last) => {
+                        components.pop();
+                        continue;
+                    }
+                    Some(last) if Utf8Component::is_root(last) => continue,
+                    _ => (),
+                }
+            }
+            components.push(c);
         }
+
+        let mut canonical = Utf8PathBuf::<T>::new();
+        for c in components {
+            canonical.push(c.as_str());
+        }
+        if canonical.as_str().is_empty() {
+            return ".".to_string();
+        }
+        canonical.into_string()
     }
 
     fn cmp_unix(&self, str1: &str, str2: &str) -> Ordering {
@@ -266,6 +888,9 @@ impl PathComparer {
         let path1 = Utf8UnixPath::new(str1);
         let path2 = Utf8UnixPath::new(str2);
 
+        if let Some(o) = self.cmp_file_names(path1, path2) {
+            return o;
+        }
         if let Some(o) = Self::cmp_absolute(path1, path2) {
             return o;
         }
@@ -279,6 +904,9 @@ impl PathComparer {
         let path1 = Utf8WindowsPath::new(str1);
         let path2 = Utf8WindowsPath::new(str2);
 
+        if let Some(o) = self.cmp_file_names(path1, path2) {
+            return o;
+        }
         if let Some(o) = Self::cmp_absolute(path1, path2) {
             return o;
         }
@@ -299,8 +927,15 @@ impl PathComparer {
                     pre1.as_str(),
                     pre2.as_str(),
                 );
-                if pre1 != pre2 {
-                    return pre1.cmp(&pre2);
+                // Drive letters are case-insensitive, so `C:` and `c:` are the
+                // same drive when comparing case-insensitively.
+                let ord = if self.case_insensitive {
+                    compare_two_strings(None, true, pre1.as_str(), pre2.as_str())
+                } else {
+                    pre1.cmp(&pre2)
+                };
+                if ord != Ordering::Equal {
+                    return ord;
                 }
             }
             (Some(Utf8WindowsComponent::Prefix(_)), _) => {
@@ -319,6 +954,83 @@ impl PathComparer {
         self.cmp_components(path1, path2)
     }
 
+    // When sorting by extension or basename, that part of the path is
+    // compared before anything else. Paths without an extension or basename
+    // come first. Paths with the same basename are then sorted by their
+    // parent directory.
+    fn cmp_file_names<T>(&self, path1: &Utf8Path<T>, path2: &Utf8Path<T>) -> Option<Ordering>
+    where
+        T: for<'enc> Utf8Encoding<'enc>,
+    {
+        let (name1, name2) = match self.order {
+            PathOrder::Extension => (path1.extension(), path2.extension()),
+            PathOrder::Basename => (path1.file_name(), path2.file_name()),
+            PathOrder::Depth | PathOrder::Tree => return None,
+        };
+        debug!(
+            "  comparing {:?} of paths: {name1:?} <=> {name2:?}",
+            self.order
+        );
+
+        let mut ord = match (name1, name2) {
+            (Some(n1), Some(n2)) => self.cmp_component(n1, n2),
+            _ => name1.is_some().cmp(&name2.is_some()),
+        };
+        if ord == Ordering::Equal && self.order == PathOrder::Basename {
+            if let (Some(parent1), Some(parent2)) = (path1.parent(), path2.parent()) {
+                ord = self.cmp_parents(parent1, parent2);
+            }
+        }
+        (ord != Ordering::Equal).then_some(ord)
+    }
+
+    // This compares parent directories component by component, so `/a/b`
+    // comes before `/b`, and a parent comes before the directories in it.
+    fn cmp_parents<T>(&self, parent1: &Utf8Path<T>, parent2: &Utf8Path<T>) -> Ordering
+    where
+        T: for<'enc> Utf8Encoding<'enc>,
+    {
+        debug!("  comparing parent directories: `{parent1}` <=> `{parent2}`");
+        let elems1 = parent1.components().collect::<Vec<_>>();
+        let elems2 = parent2.components().collect::<Vec<_>>();
+        elems1
+            .iter()
+            .zip(elems2.iter())
+            .map(|(e1, e2)| self.cmp_component(e1.as_str(), e2.as_str()))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or_else(|| elems1.len().cmp(&elems2.len()))
+    }
+
+    // Paths with a Windows prefix come first, and are compared as Windows
+    // paths. Every other path is compared as a Unix path, so Unix and
+    // Windows paths with a root come next, followed by relative paths.
+    fn cmp_mixed(&self, str1: &str, str2: &str) -> Ordering {
+        debug!("PathComparer comparing paths as mixed paths: `{str1}` <=> `{str2}`");
+
+        match (has_windows_prefix(str1), has_windows_prefix(str2)) {
+            (true, true) => return self.cmp_windows(str1, str2),
+            (true, false) => {
+                debug!("  only the left side starts with a Windows prefix");
+                return Ordering::Less;
+            }
+            (false, true) => {
+                debug!("  only the right side starts with a Windows prefix");
+                return Ordering::Greater;
+            }
+            (false, false) => (),
+        }
+
+        // Paths like `a\b` and `a/b` are the same once they're turned into
+        // Unix paths, so they are sorted as text to give them a stable order.
+        // With `--normalize`, `cmp` breaks these ties itself.
+        let ord = self.cmp_unix(&Self::as_unix_path(str1), &Self::as_unix_path(str2));
+        if self.normalize {
+            ord
+        } else {
+            ord.then_with(|| str1.cmp(str2))
+        }
+    }
+
     fn cmp_absolute<T>(path1: &Utf8Path<T>, path2: &Utf8Path<T>) -> Option<Ordering>
     where
         T: for<'enc> Utf8Encoding<'enc>,
@@ -362,7 +1074,7 @@ impl PathComparer {
             _ => (),
         }
 
-        if elems1.len() != elems2.len() {
+        if self.order != PathOrder::Tree && elems1.len() != elems2.len() {
             debug!(
                 "  the sides differ in numbers of elements: {} <=> {}",
                 elems1.len(),
@@ -372,72 +1084,774 @@ impl PathComparer {
         }
 
         debug!("  comparing each component in turn");
-        for i in 0..elems1.len() {
+        for i in 0..elems1.len().min(elems2.len()) {
             let elem1_str = elems1[i].as_str();
             let elem2_str = elems2[i].as_str();
-            let ord = compare_two_strings(
-                self.collator.as_ref(),
-                self.case_insensitive,
-                elem1_str,
-                elem2_str,
-            );
+            let ord = self.cmp_component(elem1_str, elem2_str);
             debug!("  {i}: `{elem1_str}` <=> `{elem2_str}`: {ord:?}");
-            if ord != Ordering::Equal {
-                return ord;
+            if ord == Ordering::Equal {
+                continue;
             }
+
+            if self.dirs_first {
+                // A component is a directory if there are more components
+                // after it, or if it's the last component and the path has a
+                // trailing separator.
+                let is_dir1 = i + 1 < elems1.len() || self.has_trailing_separator(path1.as_str());
+                let is_dir2 = i + 1 < elems2.len() || self.has_trailing_separator(path2.as_str());
+                if is_dir1 != is_dir2 {
+                    debug!("  only one side is a directory at component {i}");
+                    return is_dir2.cmp(&is_dir1);
+                }
+            }
+            return ord;
         }
 
-        debug!("  no differences in path found");
-        Ordering::Equal
+        // In tree order, a path sorts before the paths it contains. In depth
+        // order, we only get here if both paths have the same number of
+        // components.
+        debug!("  no differences in path found up to the shorter path's length");
+        elems1.len().cmp(&elems2.len())
+    }
+}
+
+pub(crate) struct IpComparer {
+    map_ipv4: bool,
+}
+
+impl Comparer for IpComparer {
+    fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
+        let ip1 = map_ipv4_address(Self::parse_ip_address(str1)?, self.map_ipv4);
+        let ip2 = map_ipv4_address(Self::parse_ip_address(str2)?, self.map_ipv4);
+        Ok(compare_two_ip_addresses(ip1, ip2))
+    }
+
+    fn check_line(&self, str: &str) -> Result<()> {
+        Self::parse_ip_address(str).map(|_| ())
+    }
+
+    fn canonicalize(&self, str: &str) -> Result<String> {
+        Ok(map_ipv4_address(Self::parse_ip_address(str)?, self.map_ipv4).to_string())
+    }
+}
+
+impl IpComparer {
+    pub(crate) fn new(map_ipv4: bool) -> Self {
+        Self { map_ipv4 }
+    }
+
+    pub(crate) fn parse_ip_address(addr: &str) -> Result<IpAddr> {
+        Ok(addr.parse::<IpAddr>()?)
+    }
+}
+
+pub(crate) struct EndpointComparer {
+    map_ipv4: bool,
+}
+
+impl Comparer for EndpointComparer {
+    fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
+        let ep1 = Self::parse_endpoint(str1)?;
+        let ep2 = Self::parse_endpoint(str2)?;
+
+        let cmp = compare_two_ip_addresses(
+            map_ipv4_address(ep1.addr, self.map_ipv4),
+            map_ipv4_address(ep2.addr, self.map_ipv4),
+        );
+        if cmp != Ordering::Equal {
+            return Ok(cmp);
+        }
+
+        // An endpoint without a zone or port sorts before one with it.
+        let cmp = ep1.zone.cmp(&ep2.zone);
+        if cmp != Ordering::Equal {
+            return Ok(cmp);
+        }
+        Ok(ep1.port.cmp(&ep2.port))
+    }
+
+    fn check_line(&self, str: &str) -> Result<()> {
+        Self::parse_endpoint(str).map(|_| ())
+    }
+
+    fn canonicalize(&self, str: &str) -> Result<String> {
+        let mut ep = Self::parse_endpoint(str)?;
+        ep.addr = map_ipv4_address(ep.addr, self.map_ipv4);
+        Ok(ep.to_string())
+    }
+}
+
+impl EndpointComparer {
+    pub(crate) fn new(map_ipv4: bool) -> Self {
+        Self { map_ipv4 }
+    }
+
+    // This accepts an IPv4 address with an optional port (`192.0.2.1:443`),
+    // an IPv6 address with an optional zone (`fe80::1%eth0`), or an IPv6
+    // address with an optional zone in brackets followed by an optional port
+    // (`[2001:db8::1]:443`).
+    pub(crate) fn parse_endpoint(str: &str) -> Result<Endpoint> {
+        if let Some(rest) = str.strip_prefix('[') {
+            let Some((host, after)) = rest.split_once(']') else {
+                return Err(anyhow!(
+                    "the opening bracket does not have a closing bracket"
+                ));
+            };
+            let (addr, zone) = Self::parse_addr_and_zone(host)?;
+            if !addr.is_ipv6() {
+                return Err(anyhow!("only IPv6 addresses can be written in brackets"));
+            }
+            let port = if after.is_empty() {
+                None
+            } else if let Some(port) = after.strip_prefix(':') {
+                Some(Self::parse_port(port)?)
+            } else {
+                return Err(anyhow!(
+                    "unexpected text after the closing bracket: `{after}`"
+                ));
+            };
+            return Ok(Endpoint { addr, zone, port });
+        }
+
+        // An IPv6 address without brackets cannot have a port, since the
+        // port would be ambiguous.
+        if str.matches(':').count() > 1 {
+            let (addr, zone) = Self::parse_addr_and_zone(str)?;
+            return Ok(Endpoint {
+                addr,
+                zone,
+                port: None,
+            });
+        }
+
+        let (addr, port) = match str.split_once(':') {
+            Some((addr, port)) => (addr, Some(Self::parse_port(port)?)),
+            None => (str, None),
+        };
+        Ok(Endpoint {
+            addr: IpComparer::parse_ip_address(addr)?,
+            zone: None,
+            port,
+        })
+    }
+
+    fn parse_addr_and_zone(str: &str) -> Result<(IpAddr, Option<String>)> {
+        let (addr, zone) = match str.split_once('%') {
+            Some((addr, zone)) => {
+                if zone.is_empty() {
+                    return Err(anyhow!("the zone after the `%` is empty"));
+                }
+                (addr, Some(zone.to_string()))
+            }
+            None => (str, None),
+        };
+        let addr = IpComparer::parse_ip_address(addr)?;
+        if zone.is_some() && !addr.is_ipv6() {
+            return Err(anyhow!("only IPv6 addresses can have a zone"));
+        }
+        Ok((addr, zone))
+    }
+
+    fn parse_port(port: &str) -> Result<u16> {
+        port.parse::<u16>()
+            .map_err(|e| anyhow!("the port `{port}` is not valid: {e}"))
+    }
+}
+
+// An IP address with an optional zone and port, parsed by
+// `EndpointComparer::parse_endpoint`.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Endpoint {
+    pub(crate) addr: IpAddr,
+    pub(crate) zone: Option<String>,
+    pub(crate) port: Option<u16>,
+}
+
+// This is the canonical form of the endpoint. IPv6 addresses are only
+// written in brackets when there is a port.
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let zone = self
+            .zone
+            .as_ref()
+            .map(|z| format!("%{z}"))
+            .unwrap_or_default();
+        match (self.addr, self.port) {
+            (IpAddr::V6(addr), Some(port)) => write!(f, "[{addr}{zone}]:{port}"),
+            (addr, Some(port)) => write!(f, "{addr}:{port}"),
+            (addr, None) => write!(f, "{addr}{zone}"),
+        }
+    }
+}
+
+// If `map_ipv4` is true then this turns an IPv4-mapped IPv6 address, like
+// `::ffff:192.0.2.1`, into the IPv4 address it maps to.
+pub(crate) fn map_ipv4_address(ip: IpAddr, map_ipv4: bool) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) if map_ipv4 => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        _ => ip,
+    }
+}
+
+pub(crate) struct IpTextComparer {
+    collator: Option<Collator>,
+    case_insensitive: bool,
+}
+
+impl Comparer for IpTextComparer {
+    fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
+        debug!("IpTextComparer comparing `{str1}` <=> `{str2}`");
+
+        match (Self::ip_and_rest(str1), Self::ip_and_rest(str2)) {
+            (Some((ip1, rest1)), Some((ip2, rest2))) => {
+                debug!("  Both strings start with an IP address: `{ip1}` <=> `{ip2}`");
+                let ord = compare_two_ip_addresses(ip1, ip2);
+                if ord != Ordering::Equal {
+                    return Ok(ord);
+                }
+                debug!("  The IP addresses are equal so the comparison will look at the rest of each string");
+                Ok(compare_two_strings(
+                    self.collator.as_ref(),
+                    self.case_insensitive,
+                    rest1,
+                    rest2,
+                ))
+            }
+            (Some(_), None) => {
+                debug!("  Only the left side starts with an IP address");
+                Ok(Ordering::Less)
+            }
+            (None, Some(_)) => {
+                debug!("  Only the right side starts with an IP address");
+                Ok(Ordering::Greater)
+            }
+            (None, None) => {
+                debug!("  Neither side starts with an IP address, comparing the values as strings");
+                Ok(compare_two_strings(
+                    self.collator.as_ref(),
+                    self.case_insensitive,
+                    str1,
+                    str2,
+                ))
+            }
+        }
+    }
+
+    fn validate_line(&self, str: &str) -> Result<()> {
+        if Self::ip_and_rest(str).is_some() {
+            Ok(())
+        } else {
+            Err(anyhow!("the line does not start with an IP address"))
+        }
+    }
+}
+
+impl IpTextComparer {
+    pub(crate) fn new(collator: Option<Collator>, case_insensitive: bool) -> Self {
+        Self {
+            collator,
+            case_insensitive,
+        }
+    }
+
+    // The IP address must be followed by whitespace or the end of the line.
+    // The whitespace between the address and the rest of the line is not
+    // included in the rest.
+    pub(crate) fn ip_and_rest(str: &str) -> Option<(IpAddr, &str)> {
+        let (ip, rest) = str.split_once(char::is_whitespace).unwrap_or((str, ""));
+        let ip = IpComparer::parse_ip_address(ip).ok()?;
+        Some((ip, rest.trim_start()))
+    }
+}
+
+pub(crate) struct NetworkComparer;
+
+impl Comparer for NetworkComparer {
+    fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
+        let net1 = Self::parse_network(str1)?;
+        let net2 = Self::parse_network(str2)?;
+
+        let cmp = compare_two_ip_addresses(net1.addr(), net2.addr());
+        if cmp != Ordering::Equal {
+            return Ok(cmp);
+        }
+
+        // If two networks start at the same address then the larger one
+        // comes first.
+        let cmp = compare_two_ip_addresses(net1.first(), net2.first());
+        if cmp != Ordering::Equal {
+            return Ok(cmp);
+        }
+        Ok(compare_two_ip_addresses(net2.last(), net1.last()))
+    }
+
+    fn check_line(&self, str: &str) -> Result<()> {
+        Self::parse_network(str).map(|_| ())
+    }
+
+    fn validate_line(&self, str: &str) -> Result<()> {
+        let net = Self::parse_network(str)?;
+        if let Network::Cidr(net) = net {
+            if net.trunc() != net {
+                return Err(anyhow!(
+                    "the network has host bits set, the network address is {}",
+                    net.trunc(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn canonicalize(&self, str: &str) -> Result<String> {
+        Ok(Self::parse_network(str)?.to_string())
+    }
+}
+
+impl NetworkComparer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+
+    // This accepts a network in CIDR notation (`192.0.2.0/24`), a bare
+    // address (`192.0.2.7`), a range of addresses (`192.0.2.10-192.0.2.20`),
+    // or an IPv4 address followed by a netmask (`192.0.2.0 255.255.255.0`).
+    pub(crate) fn parse_network(addr: &str) -> Result<Network> {
+        if let Some((first, last)) = addr.split_once('-') {
+            let first = IpComparer::parse_ip_address(first.trim())?;
+            let last = IpComparer::parse_ip_address(last.trim())?;
+            if first.is_ipv4() != last.is_ipv4() {
+                return Err(anyhow!(
+                    "the start and end of the range must both be IPv4 or both be IPv6 addresses"
+                ));
+            }
+            if first > last {
+                return Err(anyhow!(
+                    "the start of the range, {first}, is after the end, {last}"
+                ));
+            }
+            return Ok(Network::Range(first, last));
+        }
+
+        if let Some((ip, netmask)) = addr.split_once(char::is_whitespace) {
+            let ip = IpComparer::parse_ip_address(ip)?;
+            let netmask = IpComparer::parse_ip_address(netmask.trim())?;
+            return Ok(Network::Cidr(IpNet::with_netmask(ip, netmask)?));
+        }
+
+        if addr.contains('/') {
+            return Ok(Network::Cidr(addr.parse::<IpNet>()?));
+        }
+
+        Ok(Network::Host(IpComparer::parse_ip_address(addr)?))
+    }
+}
+
+// A network parsed by `NetworkComparer::parse_network`. A network in netmask
+// notation is turned into a `Cidr`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Network {
+    Host(IpAddr),
+    Cidr(IpNet),
+    Range(IpAddr, IpAddr),
+}
+
+impl Network {
+    // This is the address as written, so for a CIDR network this may have
+    // host bits set. Networks are sorted by this address first.
+    pub(crate) fn addr(&self) -> IpAddr {
+        match self {
+            Network::Host(ip) | Network::Range(ip, _) => *ip,
+            Network::Cidr(net) => net.addr(),
+        }
+    }
+
+    pub(crate) fn first(&self) -> IpAddr {
+        match self {
+            Network::Host(ip) | Network::Range(ip, _) => *ip,
+            Network::Cidr(net) => net.network(),
+        }
+    }
+
+    pub(crate) fn last(&self) -> IpAddr {
+        match self {
+            Network::Host(ip) | Network::Range(_, ip) => *ip,
+            Network::Cidr(net) => net.broadcast(),
+        }
+    }
+
+    // Returns true if both networks contain exactly the same addresses.
+    pub(crate) fn same_addresses(&self, other: &Network) -> bool {
+        self.first() == other.first() && self.last() == other.last()
+    }
+
+    pub(crate) fn contains(&self, other: &Network) -> bool {
+        self.first().is_ipv4() == other.first().is_ipv4()
+            && self.first() <= other.first()
+            && other.last() <= self.last()
+    }
+
+    pub(crate) fn overlaps(&self, other: &Network) -> bool {
+        self.first().is_ipv4() == other.first().is_ipv4()
+            && self.first() <= other.last()
+            && other.first() <= self.last()
+    }
+
+    // Returns the network in CIDR notation, if it can be written that way.
+    pub(crate) fn as_cidr(&self) -> Option<IpNet> {
+        match self {
+            Network::Host(ip) => Some(IpNet::from(*ip)),
+            Network::Cidr(net) => Some(net.trunc()),
+            Network::Range(first, last) => {
+                let mut subnets: Vec<IpNet> = match (first, last) {
+                    (IpAddr::V4(first), IpAddr::V4(last)) => {
+                        Ipv4Subnets::new(*first, *last, 0).map(IpNet::V4).collect()
+                    }
+                    (IpAddr::V6(first), IpAddr::V6(last)) => {
+                        Ipv6Subnets::new(*first, *last, 0).map(IpNet::V6).collect()
+                    }
+                    // `parse_network` does not allow this.
+                    _ => unreachable!("a range must not mix IPv4 and IPv6 addresses"),
+                };
+                if subnets.len() == 1 {
+                    subnets.pop()
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+// This is the canonical form of the network. Host bits are cleared for CIDR
+// networks.
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::Host(ip) => write!(f, "{ip}"),
+            Network::Cidr(net) => write!(f, "{}", net.trunc()),
+            Network::Range(first, last) => write!(f, "{first}-{last}"),
+        }
+    }
+}
+
+pub(crate) struct DomainComparer;
+
+impl Comparer for DomainComparer {
+    fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
+        let domain1 = Self::parse_domain(str1)?;
+        let domain2 = Self::parse_domain(str2)?;
+        Ok(domain1.cmp(&domain2))
+    }
+
+    fn check_line(&self, str: &str) -> Result<()> {
+        Self::parse_domain(str).map(|_| ())
+    }
+
+    fn canonicalize(&self, str: &str) -> Result<String> {
+        Ok(Self::parse_domain(str)?.to_string())
+    }
+
+    // Domain names are case-insensitive, so `Example.COM.` and `example.com`
+    // are the same domain.
+    fn unique_key(&self, str: &str) -> Result<String> {
+        self.canonicalize(str)
+    }
+}
+
+impl DomainComparer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+
+    // A single word is also a valid domain, so this only accepts domains
+    // with more than one label and a TLD that isn't a number, which rules
+    // out words and numbers like `1.5`.
+    pub(crate) fn looks_like_domain(str: &str) -> bool {
+        Self::parse_domain(str).is_ok_and(|d| {
+            d.ascii
+                .rsplit_once('.')
+                .is_some_and(|(_, tld)| !tld.chars().all(|c| c.is_ascii_digit()))
+        })
+    }
+
+    // The domain is converted to its lowercase ASCII form, so internationalized
+    // labels are compared in their punycode form. A single trailing `.` is
+    // allowed and ignored.
+    pub(crate) fn parse_domain(str: &str) -> Result<Domain> {
+        let (wildcard, name) = match str.strip_prefix("*.") {
+            Some(name) => (true, name),
+            None => (false, str),
+        };
+        let name = name.strip_suffix('.').unwrap_or(name);
+        let ascii = domain_to_ascii_cow(name.as_bytes(), AsciiDenyList::URL)
+            .map_err(|e| anyhow!("the domain is not valid: {e}"))?;
+        if ascii.is_empty() {
+            return Err(anyhow!("the domain is empty"));
+        }
+        if let Some(label) = ascii
+            .split('.')
+            .find(|l| !regex!(r"\A[a-z0-9_-]+\z").is_match(l))
+        {
+            return Err(if label.is_empty() {
+                anyhow!("the domain contains an empty label")
+            } else {
+                anyhow!("the domain contains an invalid label: `{label}`")
+            });
+        }
+
+        Ok(Domain {
+            wildcard,
+            ascii: ascii.into_owned(),
+        })
+    }
+}
+
+// A domain parsed by `DomainComparer::parse_domain`. Domains are ordered by
+// their labels starting from the TLD, so subdomains sort right after their
+// parent domain. A wildcard domain sorts after the domain it is a wildcard
+// for and before any of that domain's subdomains.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Domain {
+    wildcard: bool,
+    ascii: String,
+}
+
+impl Domain {
+    pub(crate) fn is_wildcard(&self) -> bool {
+        self.wildcard
+    }
+
+    fn reversed_labels(&self) -> impl Iterator<Item = &str> {
+        self.ascii.rsplit('.')
+    }
+}
+
+impl Ord for Domain {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.reversed_labels()
+            .cmp(other.reversed_labels())
+            .then(self.wildcard.cmp(&other.wildcard))
+    }
+}
+
+impl PartialOrd for Domain {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// This is the canonical form of the domain, which is all lowercase ASCII
+// without a trailing `.`.
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.wildcard {
+            f.write_str("*.")?;
+        }
+        f.write_str(&self.ascii)
+    }
+}
+
+pub(crate) struct EmailComparer;
+
+impl Comparer for EmailComparer {
+    fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
+        let email1 = Self::parse_email(str1)?;
+        let email2 = Self::parse_email(str2)?;
+
+        let cmp = email1.domain.cmp(&email2.domain);
+        if cmp != Ordering::Equal {
+            return Ok(cmp);
+        }
+        let cmp = email1.local.cmp(email2.local);
+        if cmp != Ordering::Equal {
+            return Ok(cmp);
+        }
+
+        // If the addresses are the same then we compare the lines as
+        // strings, which orders lines with different display names.
+        Ok(str1.cmp(str2))
+    }
+
+    fn check_line(&self, str: &str) -> Result<()> {
+        Self::parse_email(str).map(|_| ())
     }
-}
 
-pub(crate) struct IpComparer;
+    fn canonicalize(&self, str: &str) -> Result<String> {
+        let email = Self::parse_email(str)?;
+        Ok(match email.name {
+            Some(name) => format!("{name} <{}@{}>", email.local, email.domain),
+            None => format!("{}@{}", email.local, email.domain),
+        })
+    }
 
-impl Comparer for IpComparer {
-    fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
-        let ip1 = Self::parse_ip_address(str1)?;
-        let ip2 = Self::parse_ip_address(str2)?;
-        Ok(compare_two_ip_addresses(ip1, ip2))
+    // The domain is case-insensitive, so `user@Example.COM` and
+    // `user@example.com` are the same address.
+    fn unique_key(&self, str: &str) -> Result<String> {
+        self.canonicalize(str)
     }
 }
 
-impl IpComparer {
+impl EmailComparer {
     pub(crate) fn new() -> Self {
         Self
     }
 
-    fn parse_ip_address(addr: &str) -> Result<IpAddr> {
-        if addr.contains('.') {
-            Ok(IpAddr::V4(addr.parse::<Ipv4Addr>()?))
-        } else {
-            Ok(IpAddr::V6(addr.parse::<Ipv6Addr>()?))
+    // This accepts a bare address (`user@example.com`) or an address with a
+    // display name (`Some User <user@example.com>`).
+    pub(crate) fn parse_email(str: &str) -> Result<Email<'_>> {
+        let (name, addr) = match regex!(r"\A(?P<name>.*?)\s*<(?P<addr>[^<>]*)>\z").captures(str) {
+            Some(caps) => {
+                let name = caps.name("name").unwrap().as_str();
+                (
+                    if name.is_empty() { None } else { Some(name) },
+                    caps.name("addr").unwrap().as_str(),
+                )
+            }
+            None => (None, str),
+        };
+
+        let Some((local, domain)) = addr.rsplit_once('@') else {
+            return Err(anyhow!("the address does not contain an `@`"));
+        };
+        if local.is_empty() {
+            return Err(anyhow!("the address has an empty local part"));
+        }
+        if local.chars().any(char::is_whitespace) {
+            return Err(anyhow!("the local part of the address contains whitespace"));
+        }
+        let domain = DomainComparer::parse_domain(domain)?;
+        if domain.is_wildcard() {
+            return Err(anyhow!("the domain of an address cannot be a wildcard"));
         }
+
+        Ok(Email {
+            name,
+            local,
+            domain,
+        })
     }
 }
 
-pub(crate) struct NetworkComparer;
+// An email address parsed by `EmailComparer::parse_email`.
+#[derive(Debug)]
+pub(crate) struct Email<'a> {
+    name: Option<&'a str>,
+    local: &'a str,
+    domain: Domain,
+}
 
-impl Comparer for NetworkComparer {
+pub(crate) struct UrlComparer {
+    paths: PathComparer,
+}
+
+impl Comparer for UrlComparer {
     fn cmp(&self, str1: &str, str2: &str) -> Result<Ordering> {
-        let net1 = Self::parse_network(str1)?;
-        let net2 = Self::parse_network(str2)?;
+        debug!("UrlComparer comparing `{str1}` <=> `{str2}`");
 
-        let cmp = compare_two_ip_addresses(net1.addr(), net2.addr());
+        let url1 = Self::parse_url(str1)?;
+        let url2 = Self::parse_url(str2)?;
+
+        let cmp = UrlHost::from_url(&url1).cmp(&UrlHost::from_url(&url2));
+        if cmp != Ordering::Equal {
+            debug!("  The hosts are different");
+            return Ok(cmp);
+        }
+        let cmp = url1.scheme().cmp(url2.scheme());
+        if cmp != Ordering::Equal {
+            debug!("  The schemes are different");
+            return Ok(cmp);
+        }
+        // This treats an explicit default port the same as no port.
+        let cmp = url1
+            .port_or_known_default()
+            .cmp(&url2.port_or_known_default());
+        if cmp != Ordering::Equal {
+            debug!("  The ports are different");
+            return Ok(cmp);
+        }
+        let cmp = self.paths.cmp_unix(url1.path(), url2.path());
         if cmp != Ordering::Equal {
             return Ok(cmp);
         }
+        let cmp = url1.query().cmp(&url2.query());
+        if cmp != Ordering::Equal {
+            debug!("  The queries are different");
+            return Ok(cmp);
+        }
+
+        // URLs with the same canonical form are the same for `--unique`, so
+        // this keeps them next to each other.
+        let cmp = self.canonicalize(str1)?.cmp(&self.canonicalize(str2)?);
+        if cmp != Ordering::Equal {
+            debug!("  The canonical forms are different");
+            return Ok(cmp);
+        }
+
+        debug!("  The URLs are the same up to the query, comparing the values as strings");
+        Ok(str1.cmp(str2))
+    }
+
+    fn check_line(&self, str: &str) -> Result<()> {
+        Self::parse_url(str).map(|_| ())
+    }
+
+    // Parsing the URL lowercases the scheme and host and removes a default
+    // port. We also remove a trailing slash from the path.
+    fn canonicalize(&self, str: &str) -> Result<String> {
+        let mut url = Self::parse_url(str)?;
+        let path = url.path();
+        if path.len() > 1 && path.ends_with('/') {
+            let trimmed = path.trim_end_matches('/').to_string();
+            url.set_path(&trimmed);
+        }
+        Ok(url.to_string())
+    }
 
-        Ok(net1.prefix_len().cmp(&net2.prefix_len()))
+    // URLs that only differ by a default port, a trailing slash, or the case
+    // of their scheme and host are the same URL.
+    fn unique_key(&self, str: &str) -> Result<String> {
+        self.canonicalize(str)
     }
 }
 
-impl NetworkComparer {
+impl UrlComparer {
     pub(crate) fn new() -> Self {
-        Self
+        Self {
+            paths: PathComparer::new(None, false, PathOptions::default()),
+        }
+    }
+
+    pub(crate) fn parse_url(str: &str) -> Result<Url> {
+        Ok(Url::parse(str)?)
     }
-    fn parse_network(addr: &str) -> Result<IpNet> {
-        Ok(addr.parse::<IpNet>()?)
+
+    // Strings like `C:\foo` and `note: text` parse as URLs with a scheme and
+    // nothing else, so this only accepts URLs with a host.
+    pub(crate) fn looks_like_url(str: &str) -> bool {
+        Self::parse_url(str).is_ok_and(|u| u.has_host())
+    }
+}
+
+// The host of a URL, in the order that they are sorted. URLs without a host,
+// like `mailto:` URLs, come first, then IP addresses, then domains. Any host
+// which we cannot parse as a domain comes last.
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum UrlHost {
+    None,
+    Ip(IpAddr),
+    Domain(Domain),
+    Other(String),
+}
+
+impl UrlHost {
+    fn from_url(url: &Url) -> Self {
+        match url.host() {
+            None => UrlHost::None,
+            Some(Host::Ipv4(ip)) => UrlHost::Ip(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => UrlHost::Ip(IpAddr::V6(ip)),
+            Some(Host::Domain(domain)) => DomainComparer::parse_domain(domain)
+                .map_or_else(|_| UrlHost::Other(domain.to_string()), UrlHost::Domain),
+        }
     }
 }
 
@@ -465,7 +1879,54 @@ fn compare_two_strings(
     }
 }
 
-fn compare_two_ip_addresses(ip1: IpAddr, ip2: IpAddr) -> Ordering {
+// This splits each string into runs of digits and runs of everything else.
+// Runs of digits are compared by their numeric value, so `file2` comes before
+// `file10` and `v1.9` comes before `v1.10`. Everything else is compared with
+// `compare_two_strings`.
+fn compare_two_strings_naturally(
+    collator: Option<&Collator>,
+    case_insensitive: bool,
+    str1: &str,
+    str2: &str,
+) -> Ordering {
+    let chunk_re = regex!(r"[0-9]+|[^0-9]+");
+    let mut chunks1 = chunk_re.find_iter(str1).map(|m| m.as_str());
+    let mut chunks2 = chunk_re.find_iter(str2).map(|m| m.as_str());
+    loop {
+        let (left, right) = match (chunks1.next(), chunks2.next()) {
+            (None, None) => break,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) => (l, r),
+        };
+        let is_digits = |c: &str| c.starts_with(|c: char| c.is_ascii_digit());
+        let ord = if is_digits(left) && is_digits(right) {
+            compare_two_digit_strings(left, right)
+        } else {
+            compare_two_strings(collator, case_insensitive, left, right)
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+
+    // Strings like `a01` and `a1` have the same numeric value, so we fall
+    // back to comparing them as text to give them a stable order.
+    compare_two_strings(collator, case_insensitive, str1, str2)
+}
+
+// This compares two strings of ASCII digits by their numeric value without
+// parsing them, so there is no limit on the number of digits.
+fn compare_two_digit_strings(str1: &str, str2: &str) -> Ordering {
+    let trimmed1 = str1.trim_start_matches('0');
+    let trimmed2 = str2.trim_start_matches('0');
+    trimmed1
+        .len()
+        .cmp(&trimmed2.len())
+        .then_with(|| trimmed1.cmp(trimmed2))
+}
+
+pub(crate) fn compare_two_ip_addresses(ip1: IpAddr, ip2: IpAddr) -> Ordering {
     match (ip1, ip2) {
         (IpAddr::V4(_), IpAddr::V6(_)) => return Ordering::Less,
         (IpAddr::V6(_), IpAddr::V4(_)) => return Ordering::Greater,
@@ -494,10 +1955,13 @@ fn octets_for_ip_address(ip: IpAddr) -> [u8; 16] {
 #[cfg(test)]
 mod test {
     use super::{
-        Comparer, DatetimeTextComparer, IpComparer, NetworkComparer, NumberedTextComparer,
-        PathComparer, PathType, TextComparer,
+        Comparer, DateOrder, DatetimeTextComparer, DomainComparer, EmailComparer, EndpointComparer,
+        IpComparer, IpTextComparer, NetworkComparer, NumberedTextComparer, PathComparer,
+        PathOptions, PathOrder, PathType, TextComparer, Timezone, UrlComparer,
     };
     use crate::collation::collator_for_locale;
+    use anyhow::Result;
+    use std::cmp::Ordering;
     use test_log::test;
 
     struct Case {
@@ -554,12 +2018,128 @@ mod test {
                     .locale
                     .map(|l| collator_for_locale(l, c.case_insensitive).unwrap()),
                 case_insensitive: c.case_insensitive,
+                formats: vec![],
+                timezone: Timezone::default(),
+                date_order: DateOrder::default(),
             };
             c.input.sort_by(|a, b| dtc.cmp(a, b).unwrap());
             assert_eq!(c.input, c.expect);
         }
     }
 
+    #[test]
+    fn datetime_text_comparer_with_formats() -> Result<()> {
+        let dtc = DatetimeTextComparer::new(
+            None,
+            false,
+            &[
+                "%d/%m/%Y %H:%M".to_string(),
+                "%Y.%m.%d".to_string(),
+                "%s".to_string(),
+            ],
+            Timezone::default(),
+            DateOrder::default(),
+        )?;
+        let mut lines = vec![
+            "no datetime",
+            "17/10/2026 09:30 later",
+            "2026-10-16 not a listed format",
+            "1792000000 epoch",
+            "2026.10.17 midnight",
+            "2026.10.17x is not a datetime",
+            "17/10/2026 09:15",
+        ];
+        lines.sort_by(|a, b| dtc.cmp(a, b).unwrap());
+        assert_eq!(
+            lines,
+            [
+                "1792000000 epoch",
+                "2026.10.17 midnight",
+                "17/10/2026 09:15",
+                "17/10/2026 09:30 later",
+                "2026-10-16 not a listed format",
+                "2026.10.17x is not a datetime",
+                "no datetime",
+            ],
+        );
+
+        assert!(dtc.validate_line("17/10/2026 09:30").is_ok());
+        assert_eq!(
+            dtc.validate_line("2026-10-16").unwrap_err().to_string(),
+            "the line does not start with a datetime in any of these formats: \
+             `%d/%m/%Y %H:%M`, `%Y.%m.%d`, `%s`",
+        );
+
+        assert!(DatetimeTextComparer::new(
+            None,
+            false,
+            &["%Y-%Q".to_string()],
+            Timezone::default(),
+            DateOrder::default(),
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn datetime_text_comparer_with_timezone() -> Result<()> {
+        let new = |formats: &[&str], timezone: &str| -> Result<DatetimeTextComparer> {
+            DatetimeTextComparer::new(
+                None,
+                false,
+                &formats.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                timezone.parse()?,
+                DateOrder::default(),
+            )
+        };
+
+        // Midnight in Berlin is 22:00 UTC the day before in the summer, and
+        // midnight in Chicago is 05:00 UTC.
+        let utc = "2026-06-30T23:00:00+00:00";
+        for (timezone, expect) in [
+            ("Europe/Berlin", ["2026-07-01", utc]),
+            ("America/Chicago", [utc, "2026-07-01"]),
+            ("+05:30", ["2026-07-01", utc]),
+            ("-05:00", [utc, "2026-07-01"]),
+        ] {
+            for formats in [&[][..], &["%Y-%m-%dT%H:%M:%S%:z", "%Y-%m-%d"]] {
+                let dtc = new(formats, timezone)?;
+                let mut lines = vec!["2026-07-01", utc];
+                lines.sort_by(|a, b| dtc.cmp(a, b).unwrap());
+                assert_eq!(
+                    lines, expect,
+                    "sorting with the {timezone} timezone and formats {formats:?}",
+                );
+            }
+        }
+
+        assert!("Mars/Olympus_Mons".parse::<Timezone>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn datetime_text_comparer_with_date_order() -> Result<()> {
+        for (date_order, expect) in [
+            (
+                DateOrder::DayFirst,
+                ["02.01.2026", "13-01-2026", "2026-01-15", "01/02/2026"],
+            ),
+            (
+                DateOrder::MonthFirst,
+                ["01/02/2026", "2026-01-15", "02.01.2026", "13-01-2026"],
+            ),
+        ] {
+            let dtc = DatetimeTextComparer::new(None, false, &[], Timezone::default(), date_order)?;
+            let mut lines = vec!["01/02/2026", "02.01.2026", "2026-01-15", "13-01-2026"];
+            lines.sort_by(|a, b| dtc.cmp(a, b).unwrap());
+            assert_eq!(lines, expect, "sorting with {date_order:?}");
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn path_comparer() {
         for mut c in cases_from(PATH_TEST_CASES) {
@@ -569,27 +2149,267 @@ mod test {
                     .locale
                     .map(|l| collator_for_locale(l, c.case_insensitive).unwrap()),
                 case_insensitive: c.case_insensitive,
-                path_type: if c.name.contains("Windows") {
+                path_type: if c.name.contains("Mixed") {
+                    PathType::Mixed
+                } else if c.name.contains("Windows") {
                     PathType::Windows
                 } else {
                     PathType::Unix
                 },
+                order: if c.name.contains("tree order") {
+                    PathOrder::Tree
+                } else if c.name.contains("by extension") {
+                    PathOrder::Extension
+                } else if c.name.contains("by basename") {
+                    PathOrder::Basename
+                } else {
+                    PathOrder::Depth
+                },
+                dirs_first: c.name.contains("directories first"),
+                natural: c.name.contains("natural"),
+                normalize: c.name.contains("normalized"),
             };
             c.input.sort_by(|a, b| pc.cmp(a, b).unwrap());
             assert_eq!(c.input, c.expect);
         }
+
+        let pc = PathComparer::new(
+            None,
+            false,
+            PathOptions {
+                dirs_first: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(pc.canonicalize("./a//b/").unwrap(), "a/b/");
+        assert_eq!(pc.canonicalize("a/b").unwrap(), "a/b");
+        assert_eq!(pc.canonicalize("/").unwrap(), "/");
+        assert_eq!(pc.canonicalize("./").unwrap(), ".");
+
+        let pc = PathComparer::new(
+            None,
+            false,
+            PathOptions {
+                normalize: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(pc.canonicalize("a/c/../b").unwrap(), "a/b");
+        assert_eq!(pc.canonicalize("../a/../../b").unwrap(), "../../b");
+        assert_eq!(pc.canonicalize("/../a/..").unwrap(), "/");
+        assert_eq!(pc.canonicalize("a/..").unwrap(), ".");
+        assert_eq!(pc.unique_key("a//b/").unwrap(), "a/b");
+
+        let pc = PathComparer::new(
+            None,
+            true,
+            PathOptions {
+                path_type: PathType::Windows,
+                normalize: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(pc.canonicalize("C:/Foo/../Bar/").unwrap(), r"C:\Bar");
+        assert_eq!(pc.unique_key("C:/Foo/../Bar/").unwrap(), r"c:\bar");
+        assert_eq!(pc.cmp(r"c:\bar", "C:/BAR/").unwrap(), Ordering::Greater);
     }
 
     #[test]
     fn ip_comparer() {
         for mut c in cases_from(IP_TEST_CASES) {
             println!("# ip - {}", c.name);
-            let ic = IpComparer;
+            let ic = IpComparer {
+                map_ipv4: c.name.contains("mapped to IPv4"),
+            };
             c.input.sort_by(|a, b| ic.cmp(a, b).unwrap());
             assert_eq!(c.input, c.expect);
         }
     }
 
+    #[test]
+    fn endpoint_comparer() {
+        for mut c in cases_from(IP_TEST_CASES)
+            .into_iter()
+            .chain(cases_from(ENDPOINT_TEST_CASES))
+        {
+            println!("# endpoint - {}", c.name);
+            let ec = EndpointComparer {
+                map_ipv4: c.name.contains("mapped to IPv4"),
+            };
+            c.input.sort_by(|a, b| ec.cmp(a, b).unwrap());
+            assert_eq!(c.input, c.expect);
+        }
+    }
+
+    #[test]
+    fn parse_endpoint() {
+        let valid = [
+            ("192.0.2.1", "192.0.2.1"),
+            ("192.0.2.1:443", "192.0.2.1:443"),
+            ("::ffff:192.0.2.1", "::ffff:192.0.2.1"),
+            ("fe80::1%eth0", "fe80::1%eth0"),
+            ("[fe80::1%eth0]", "fe80::1%eth0"),
+            ("[2001:DB8::1]:443", "[2001:db8::1]:443"),
+            ("[fe80::1%eth0]:22", "[fe80::1%eth0]:22"),
+        ];
+        for (input, canonical) in valid {
+            let ep = EndpointComparer::parse_endpoint(input);
+            assert!(ep.is_ok(), "{input} is valid: {ep:?}");
+            assert_eq!(ep.unwrap().to_string(), canonical, "{input}");
+        }
+
+        let invalid = [
+            "192.0.2.1%eth0",
+            "[192.0.2.1]:443",
+            "[2001:db8::1",
+            "[2001:db8::1]443",
+            "192.0.2.1:65536",
+            "fe80::1%",
+            "host:443",
+        ];
+        for input in invalid {
+            assert!(
+                EndpointComparer::parse_endpoint(input).is_err(),
+                "{input} is invalid",
+            );
+        }
+    }
+
+    #[test]
+    fn ip_text_comparer() {
+        for mut c in cases_from(TEXT_TEST_CASES)
+            .into_iter()
+            .chain(cases_from(IP_TEXT_TEST_CASES))
+        {
+            println!("# ip text - {}", c.name);
+            let itc = IpTextComparer {
+                collator: c
+                    .locale
+                    .map(|l| collator_for_locale(l, c.case_insensitive).unwrap()),
+                case_insensitive: c.case_insensitive,
+            };
+            c.input.sort_by(|a, b| itc.cmp(a, b).unwrap());
+            assert_eq!(c.input, c.expect);
+        }
+    }
+
+    #[test]
+    fn domain_comparer() {
+        for mut c in cases_from(DOMAIN_TEST_CASES) {
+            println!("# domain - {}", c.name);
+            let dc = DomainComparer;
+            c.input.sort_by(|a, b| dc.cmp(a, b).unwrap());
+            assert_eq!(c.input, c.expect);
+        }
+    }
+
+    #[test]
+    fn parse_domain() {
+        let valid = [
+            ("example.com", "example.com"),
+            ("Example.COM.", "example.com"),
+            ("*.example.com", "*.example.com"),
+            ("_dmarc.example.com", "_dmarc.example.com"),
+            ("bücher.example", "xn--bcher-kva.example"),
+            ("xn--bcher-kva.example", "xn--bcher-kva.example"),
+        ];
+        for (input, canonical) in valid {
+            let domain = DomainComparer::parse_domain(input);
+            assert!(domain.is_ok(), "{input} is valid: {domain:?}");
+            assert_eq!(domain.unwrap().to_string(), canonical, "{input}");
+            assert_eq!(
+                DomainComparer.unique_key(input).unwrap(),
+                canonical,
+                "{input}",
+            );
+        }
+
+        let invalid = [
+            "",
+            "a..example.com",
+            "foo bar.com",
+            "*.",
+            "a*.example.com",
+            "x/y.com",
+        ];
+        for input in invalid {
+            assert!(
+                DomainComparer::parse_domain(input).is_err(),
+                "{input} is invalid",
+            );
+        }
+    }
+
+    #[test]
+    fn email_comparer() {
+        for mut c in cases_from(EMAIL_TEST_CASES) {
+            println!("# email - {}", c.name);
+            let ec = EmailComparer;
+            c.input.sort_by(|a, b| ec.cmp(a, b).unwrap());
+            assert_eq!(c.input, c.expect);
+        }
+    }
+
+    #[test]
+    fn parse_email() {
+        let valid = [
+            ("user@example.com", "user@example.com"),
+            ("User@EXAMPLE.com", "User@example.com"),
+            (
+                "Some User <user@Example.com>",
+                "Some User <user@example.com>",
+            ),
+            ("<user@example.com>", "user@example.com"),
+            ("\"at@sign\"@example.com", "\"at@sign\"@example.com"),
+        ];
+        for (input, canonical) in valid {
+            let ec = EmailComparer;
+            let email = ec.canonicalize(input);
+            assert!(email.is_ok(), "{input} is valid: {email:?}");
+            assert_eq!(email.unwrap(), canonical, "{input}");
+            assert_eq!(ec.unique_key(input).unwrap(), canonical, "{input}");
+        }
+
+        let invalid = [
+            "user",
+            "@example.com",
+            "user@",
+            "user@*.example.com",
+            "Some User user@example.com",
+            "Some User <user@example.com",
+        ];
+        for input in invalid {
+            assert!(
+                EmailComparer::parse_email(input).is_err(),
+                "{input} is invalid",
+            );
+        }
+    }
+
+    #[test]
+    fn url_comparer() {
+        for mut c in cases_from(URL_TEST_CASES) {
+            println!("# url - {}", c.name);
+            let uc = UrlComparer::new();
+            c.input.sort_by(|a, b| uc.cmp(a, b).unwrap());
+            assert_eq!(c.input, c.expect);
+        }
+
+        let uc = UrlComparer::new();
+        for (input, canonical) in [
+            ("HTTPS://Example.COM:443/a/b/", "https://example.com/a/b"),
+            ("https://example.com", "https://example.com/"),
+            (
+                "http://example.com:8080/?q=1",
+                "http://example.com:8080/?q=1",
+            ),
+        ] {
+            assert_eq!(uc.canonicalize(input).unwrap(), canonical, "{input}");
+            assert_eq!(uc.unique_key(input).unwrap(), canonical, "{input}");
+        }
+        assert!(uc.check_line("not a url").is_err());
+    }
+
     #[test]
     fn network_comparer() {
         for mut c in cases_from(NETWORK_TEST_CASES) {
@@ -813,7 +2633,7 @@ numbered text with decimal numbers
 false
 ";
 
-    const DATETIME_TEXT_TEST_CASES: &str = r"
+    const DATETIME_TEXT_TEST_CASES: &str = r#"
 datetime ASCII text with no locale
 ----
 2017-1-12 hello
@@ -899,7 +2719,47 @@ datetime and dates
 2017-1-12T14:01:01
 ----
 false
-";
+====
+datetime with a space between the date and time
+----
+2026-10-17 12:00:01 second
+2026-10-17 09:30:00 first
+2026-10-17 12:00:01 +0200 zero
+2026-10-16 23:59:59.999 zeroth
+----
+2026-10-16 23:59:59.999 zeroth
+2026-10-17 09:30:00 first
+2026-10-17 12:00:01 +0200 zero
+2026-10-17 12:00:01 second
+----
+false
+====
+datetime as a syslog timestamp
+----
+Oct 17 12:00:01 host sshd[42]: second
+Oct  9 08:00:00 host cron[1]: first
+Nov  1 00:00:00 host kernel: third
+----
+Oct  9 08:00:00 host cron[1]: first
+Oct 17 12:00:01 host sshd[42]: second
+Nov  1 00:00:00 host kernel: third
+----
+false
+====
+datetime in a Common Log Format line
+----
+192.0.2.1 - - [17/Oct/2026:12:00:01 +0000] "GET / HTTP/1.1" 200 512
+192.0.2.9 - frank [17/Oct/2026:13:00:00 +0200] "GET /b HTTP/1.1" 200 512
+[16/Oct/2026:08:00:00 +0000] "GET /a HTTP/1.1" 404 0
+no datetime [here]
+----
+[16/Oct/2026:08:00:00 +0000] "GET /a HTTP/1.1" 404 0
+192.0.2.9 - frank [17/Oct/2026:13:00:00 +0200] "GET /b HTTP/1.1" 200 512
+192.0.2.1 - - [17/Oct/2026:12:00:01 +0000] "GET / HTTP/1.1" 200 512
+no datetime [here]
+----
+false
+"#;
 
     const PATH_TEST_CASES: &str = r"
 path with ASCII text
@@ -956,6 +2816,172 @@ path with ASCII text, depth sorts before path content
 ----
 false
 ====
+path with ASCII text, tree order
+----
+/b
+/a/b
+/a/a/z
+/a
+c/d
+c
+/a/a
+----
+/a
+/a/a
+/a/a/z
+/a/b
+/b
+c
+c/d
+----
+false
+====
+path with ASCII text, directories first
+----
+/b
+/a/z
+/a/b/
+/a/c/d
+/c/
+/a/a
+----
+/c/
+/b
+/a/b/
+/a/a
+/a/z
+/a/c/d
+----
+false
+====
+path with ASCII text, tree order with directories first
+----
+/b
+/a/z
+/a/b/
+/a/c/d
+/c/
+/a/a
+/a/b/x
+----
+/a/b/
+/a/b/x
+/a/c/d
+/a/a
+/a/z
+/c/
+/b
+----
+false
+====
+path with ASCII text, by extension
+----
+src/main.rs
+README.md
+/etc/hosts
+lib.rs
+docs/Changes.md
+Makefile
+----
+/etc/hosts
+Makefile
+README.md
+docs/Changes.md
+lib.rs
+src/main.rs
+----
+false
+====
+path with ASCII text, by basename
+----
+b/mod.rs
+a/z.rs
+/x/mod.rs
+a/b/mod.rs
+lib.rs
+----
+lib.rs
+/x/mod.rs
+a/b/mod.rs
+b/mod.rs
+a/z.rs
+----
+false
+====
+path with ASCII text, natural numbers
+----
+/v1.10/file10.txt
+/v1.9/file2.txt
+/v1.9/file10.txt
+/v1.9/file02.txt
+/v1.9/file1.txt
+/v10/a
+/v2/a
+----
+/v1.9/file1.txt
+/v1.9/file02.txt
+/v1.9/file2.txt
+/v1.9/file10.txt
+/v1.10/file10.txt
+/v2/a
+/v10/a
+----
+false
+====
+Windows ASCII path in tree order with natural numbers
+----
+C:\v1.10\file10.txt
+C:\v1.9\file10.txt
+C:\v1.9\file2.txt
+C:\v1.9
+----
+C:\v1.9
+C:\v1.9\file2.txt
+C:\v1.9\file10.txt
+C:\v1.10\file10.txt
+----
+false
+====
+path with ASCII text, normalized
+----
+a/c/../b
+/x
+a//b
+a/b/
+../a/./b
+a/./b
+/../x/y
+a/a
+----
+/x
+/../x/y
+a/a
+a/./b
+a//b
+a/b/
+a/c/../b
+../a/./b
+----
+false
+====
+Windows ASCII path in tree order
+----
+C:\a\b\c
+\b
+C:\z
+C:\a
+\a\b
+C:\a\b
+----
+C:\a
+C:\a\b
+C:\a\b\c
+C:\z
+\a\b
+\b
+----
+false
+====
 Windows ASCII path
 ----
 C:\foo
@@ -978,6 +3004,28 @@ E:\a
 ----
 false
 ====
+Mixed Unix and Windows paths
+----
+rel\b
+/usr/x
+C:\a
+\b
+rel/a
+D:\z
+/a
+x
+----
+C:\a
+D:\z
+/a
+\b
+/usr/x
+x
+rel/a
+rel\b
+----
+false
+====
 Unix Unicode path with de-DE locale
 ----
 /foo
@@ -1073,6 +3121,164 @@ ip with mixed IPv4 and IPv6
 ::1234
 1234::
 9876::fe01:1234:457f
+====
+ip with IPv4-mapped IPv6
+----
+::ffff:10.0.0.1
+2001:db8::1
+192.0.2.1
+::ffff:1.2.3.4
+----
+192.0.2.1
+::ffff:1.2.3.4
+::ffff:10.0.0.1
+2001:db8::1
+====
+ip with IPv4-mapped IPv6 mapped to IPv4
+----
+::ffff:10.0.0.1
+2001:db8::1
+192.0.2.1
+::ffff:1.2.3.4
+----
+::ffff:1.2.3.4
+::ffff:10.0.0.1
+192.0.2.1
+2001:db8::1
+";
+
+    const ENDPOINT_TEST_CASES: &str = r"
+endpoints with ports and zones
+----
+[2001:db8::1]:443
+192.0.2.1:443
+fe80::1%eth1
+192.0.2.1
+[fe80::1%eth0]:22
+192.0.2.1:80
+2001:db8::1
+fe80::1
+----
+192.0.2.1
+192.0.2.1:80
+192.0.2.1:443
+2001:db8::1
+[2001:db8::1]:443
+fe80::1
+[fe80::1%eth0]:22
+fe80::1%eth1
+====
+endpoints with IPv4-mapped IPv6 mapped to IPv4
+----
+[::ffff:192.0.2.1]:443
+192.0.2.1:80
+10.0.0.1
+----
+10.0.0.1
+192.0.2.1:80
+[::ffff:192.0.2.1]:443
+";
+
+    const IP_TEXT_TEST_CASES: &str = r"
+hosts file with no locale
+----
+192.168.1.10 printer
+::1 localhost ip6-localhost
+10.0.0.1	router
+127.0.0.1 localhost
+not an ip
+127.0.0.1 Localhost
+10.0.0.1
+----
+10.0.0.1
+10.0.0.1	router
+127.0.0.1 Localhost
+127.0.0.1 localhost
+192.168.1.10 printer
+::1 localhost ip6-localhost
+not an ip
+----
+false
+====
+hosts file with no locale, case-insensitive
+----
+127.0.0.1 localhost
+127.0.0.1 Broadcasthost
+127.0.0.1  alpha
+----
+127.0.0.1  alpha
+127.0.0.1 Broadcasthost
+127.0.0.1 localhost
+----
+true
+";
+
+    const DOMAIN_TEST_CASES: &str = r"
+domains grouped by reversed labels
+----
+b.example.com
+a.example.org
+example.com
+A.example.com
+*.example.com
+*.a.example.com
+com
+xn--bcher-kva.example
+Bücher.example
+----
+com
+example.com
+*.example.com
+A.example.com
+*.a.example.com
+b.example.com
+xn--bcher-kva.example
+Bücher.example
+a.example.org
+";
+
+    const EMAIL_TEST_CASES: &str = r"
+emails grouped by domain
+----
+zed@example.com
+Bob <bob@example.org>
+alice@EXAMPLE.com
+Alice <alice@example.com>
+Bob@example.com
+carol@mail.example.com
+----
+Bob@example.com
+Alice <alice@example.com>
+alice@EXAMPLE.com
+zed@example.com
+carol@mail.example.com
+Bob <bob@example.org>
+";
+
+    const URL_TEST_CASES: &str = r"
+urls grouped by host
+----
+https://www.example.org/
+https://example.com/b
+http://example.com/a/b
+https://example.com:8443/a
+https://example.com/a?z=1
+https://example.com:443/a?a=1
+mailto:user@example.com
+https://api.example.com/
+http://192.0.2.1/
+https://example.com/a/
+----
+mailto:user@example.com
+http://192.0.2.1/
+http://example.com/a/b
+https://example.com/a/
+https://example.com:443/a?a=1
+https://example.com/a?z=1
+https://example.com/b
+https://example.com:8443/a
+https://api.example.com/
+https://www.example.org/
 ";
 
     const NETWORK_TEST_CASES: &str = r"
@@ -1123,5 +3329,23 @@ network with mixed IPv4 and IPv6
 ::1/128
 1234::/90
 9876::fe01:1234:0/24
+====
+network with hosts, ranges, and netmasks
+----
+192.0.2.10-192.0.2.20
+192.0.2.7
+192.0.2.0/24
+10.0.0.0 255.0.0.0
+192.0.2.10-192.0.2.12
+2001:db8::1
+192.0.2.10/32
+----
+10.0.0.0 255.0.0.0
+192.0.2.0/24
+192.0.2.7
+192.0.2.10-192.0.2.20
+192.0.2.10-192.0.2.12
+192.0.2.10/32
+2001:db8::1
 ";
 }
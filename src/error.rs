@@ -1,3 +1,5 @@
+use crate::sorter::Strategy;
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -14,4 +16,130 @@ pub(crate) enum CheckError {
         line2: usize,
         line: String,
     },
+    #[error(
+        "the auto-detected sorting method is not stable - sampling the file picked {sampled:?} but the whole file picks {full:?}"
+    )]
+    UnstableStrategy { sampled: Strategy, full: Strategy },
+    #[error(
+        "the given file contains invalid lines:\n{}",
+        format_invalid_lines(lines)
+    )]
+    HasInvalidLines { lines: Vec<InvalidLine> },
+    #[error(
+        r#"the given file contains a line which is not in canonical form at {line_number:} - "{line:}" should be "{canonical:}""#
+    )]
+    NotCanonical {
+        line_number: usize,
+        line: String,
+        canonical: String,
+    },
+    #[error(
+        r#"the given file contains a network at {line_number:}, "{line:}", which can be collapsed into "{network:}""#
+    )]
+    NotCollapsed {
+        line_number: usize,
+        line: String,
+        network: String,
+    },
+    #[error(
+        "the given file contains overlapping networks:\n{}",
+        format_overlaps(overlaps)
+    )]
+    HasOverlappingNetworks { overlaps: Vec<Overlap> },
+    #[error(
+        "the given file contains paths which do not exist:\n{}",
+        format_invalid_lines(lines)
+    )]
+    HasMissingPaths { lines: Vec<InvalidLine> },
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum SortError {
+    #[error(
+        "the given file contains lines which cannot be parsed:\n{}",
+        format_invalid_lines(lines)
+    )]
+    InvalidLines { lines: Vec<InvalidLine> },
+}
+
+#[derive(Debug)]
+pub(crate) struct InvalidLine {
+    pub(crate) line_number: usize,
+    pub(crate) line: String,
+    pub(crate) error: String,
+}
+
+impl fmt::Display for InvalidLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"line {}: "{}": {}"#,
+            self.line_number, self.line, self.error
+        )
+    }
+}
+
+fn format_invalid_lines(lines: &[InvalidLine]) -> String {
+    lines
+        .iter()
+        .map(|l| format!("  {l}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Debug)]
+pub(crate) struct Overlap {
+    pub(crate) first_line_number: usize,
+    pub(crate) first: String,
+    pub(crate) second_line_number: usize,
+    pub(crate) second: String,
+    pub(crate) relationship: Relationship,
+}
+
+// This describes how the second network in an `Overlap` relates to the
+// first.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum Relationship {
+    Duplicate,
+    Subset,
+    Superset,
+    Overlap,
+}
+
+impl fmt::Display for Overlap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let relationship = match self.relationship {
+            Relationship::Duplicate => "is a duplicate of",
+            Relationship::Subset => "is a subset of",
+            Relationship::Superset => "is a superset of",
+            Relationship::Overlap => "overlaps",
+        };
+        write!(
+            f,
+            r#"line {}: "{}" {} line {}: "{}""#,
+            self.second_line_number, self.second, relationship, self.first_line_number, self.first,
+        )
+    }
+}
+
+fn format_overlaps(overlaps: &[Overlap]) -> String {
+    overlaps
+        .iter()
+        .map(|o| format!("  {o}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum LookupError {
+    #[error(r#"the given file does not contain any lines matching "{}""#, values.join(r#"", ""#))]
+    NotFound { values: Vec<String> },
+}
+
+// The arguments that are only checked once `--sort auto` has picked a sorting
+// method. These are reported the same way as any other invalid arguments.
+#[derive(Debug, Error)]
+pub(crate) enum ArgsError {
+    #[error("{message}")]
+    InvalidForDetectedStrategy { message: String },
 }
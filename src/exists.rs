@@ -0,0 +1,180 @@
+use crate::{comparer::PathType, error::InvalidLine, SortableLine};
+use glob::{glob, Pattern};
+use log::debug;
+use std::path::{Path, PathBuf};
+use typed_path::{
+    Utf8Component, Utf8Encoding, Utf8Path, Utf8UnixPath, Utf8WindowsComponent, Utf8WindowsPath,
+};
+
+// Returns every line which does not refer to an existing path. Each line is
+// resolved relative to `root`, including lines which start with a separator,
+// so `/docs` is the `docs` directory in `root`, like in a `.gitignore` or
+// `CODEOWNERS` file. Only a line with a Windows prefix, like `C:\`, is
+// resolved relative to that prefix instead. A line which contains a glob
+// pattern is missing if no path has that exact name and the pattern doesn't
+// match anything, so a file like `pages/[id].tsx` is found either way. A
+// line which is not a valid pattern is reported with the pattern's error.
+pub(crate) fn missing_paths(
+    lines: &[SortableLine],
+    root: &Path,
+    path_type: PathType,
+) -> Vec<InvalidLine> {
+    let mut missing = vec![];
+    for line in lines {
+        let (base, components) = match path_type.for_line(&line.line) {
+            PathType::Windows => {
+                let path = Utf8WindowsPath::new(&line.line);
+                let prefix = match path.components().next() {
+                    Some(Utf8WindowsComponent::Prefix(p)) => {
+                        Some(PathBuf::from(format!("{}\\", p.as_str())))
+                    }
+                    _ => None,
+                };
+                (prefix, relative_components(path))
+            }
+            _ => (None, relative_components(Utf8UnixPath::new(&line.line))),
+        };
+        let base = base.unwrap_or_else(|| root.to_path_buf());
+
+        // The base is escaped so that any glob characters in it are matched
+        // literally.
+        let mut pattern = Pattern::escape(&base.to_string_lossy());
+        let path = components.iter().fold(base, |path, c| path.join(c));
+        debug!("checking whether `{}` exists", path.display());
+        if path.exists() {
+            continue;
+        }
+
+        let error = if is_glob(&line.line) {
+            for c in &components {
+                // An empty base is the current directory, and a separator
+                // here would make the pattern absolute.
+                if !pattern.is_empty() {
+                    pattern.push('/');
+                }
+                pattern.push_str(c);
+            }
+            debug!("checking whether the glob `{pattern}` matches any paths");
+            match glob(&pattern) {
+                Ok(mut paths) => {
+                    if paths.any(|p| p.is_ok()) {
+                        continue;
+                    }
+                    "no paths match this pattern".to_string()
+                }
+                Err(e) => format!("this is not a valid glob pattern: {e}"),
+            }
+        } else {
+            "this path does not exist".to_string()
+        };
+        missing.push(InvalidLine {
+            line_number: line.line_number,
+            line: line.line.clone(),
+            error,
+        });
+    }
+
+    missing
+}
+
+// Returns the components of the path after any prefix or root, so that the
+// path can be joined to another path.
+fn relative_components<T>(path: &Utf8Path<T>) -> Vec<&str>
+where
+    T: for<'enc> Utf8Encoding<'enc>,
+{
+    path.components()
+        .filter(|c| c.is_normal() || c.is_parent())
+        .map(|c| c.as_str())
+        .collect()
+}
+
+fn is_glob(line: &str) -> bool {
+    line.contains(['*', '?', '['])
+}
+
+#[cfg(test)]
+mod test {
+    use super::missing_paths;
+    use crate::{comparer::PathType, SortableLine};
+    use anyhow::Result;
+    use std::{
+        fs::{create_dir_all, write},
+        path::Path,
+    };
+    use tempfile::tempdir;
+    use test_log::test;
+
+    #[test]
+    fn missing_paths_in_root() -> Result<()> {
+        let td = tempdir()?;
+        create_dir_all(td.path().join("src/sub"))?;
+        write(td.path().join("src/main.rs"), "")?;
+        write(td.path().join("src/sub/mod.rs"), "")?;
+        write(td.path().join("README.md"), "")?;
+        create_dir_all(td.path().join("pages"))?;
+        write(td.path().join("pages/[id].tsx"), "")?;
+
+        let lines = SortableLine::numbered(&[
+            "README.md",
+            "/src/",
+            "src/main.rs",
+            "./src/../README.md",
+            "src/*.rs",
+            "src/**/mod.rs",
+            "Changes.md",
+            "/src/lib.rs",
+            "*.txt",
+            "src/sub/[a-l]*.rs",
+            "pages/[id].tsx",
+            "src/[.rs",
+        ]);
+
+        let missing = missing_paths(&lines, td.path(), PathType::Unix);
+        assert_eq!(
+            missing
+                .iter()
+                .map(|l| (l.line_number, l.line.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (7, "Changes.md"),
+                (8, "/src/lib.rs"),
+                (9, "*.txt"),
+                (10, "src/sub/[a-l]*.rs"),
+                (12, "src/[.rs"),
+            ],
+        );
+        assert_eq!(missing[0].error, "this path does not exist");
+        assert_eq!(missing[2].error, "no paths match this pattern");
+        assert!(
+            missing[4]
+                .error
+                .starts_with("this is not a valid glob pattern"),
+            "{}",
+            missing[4].error,
+        );
+
+        let lines = SortableLine::numbered(&[r"src\main.rs", r"\src\sub", r"src\nope"]);
+        let missing = missing_paths(&lines, td.path(), PathType::Windows);
+        assert_eq!(
+            missing.iter().map(|l| l.line_number).collect::<Vec<_>>(),
+            [3],
+        );
+
+        Ok(())
+    }
+
+    // The parent of a bare file name is an empty path, so the paths are
+    // resolved relative to the current directory, which is the crate root
+    // when running tests.
+    #[test]
+    fn missing_paths_in_empty_root() {
+        let lines = SortableLine::numbered(&["Cargo.toml", "src/*.rs", "nope.toml", "src/*.nope"]);
+
+        let missing = missing_paths(&lines, Path::new(""), PathType::Unix);
+        assert_eq!(
+            missing.iter().map(|l| l.line_number).collect::<Vec<_>>(),
+            [3, 4],
+        );
+    }
+}
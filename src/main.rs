@@ -3,16 +3,19 @@ extern crate alloc;
 mod collation;
 mod comparer;
 mod error;
+mod exists;
 mod logging;
 mod sorter;
 
-use crate::error::CheckError;
+use crate::error::{ArgsError, CheckError, InvalidLine, LookupError};
 use anyhow::{anyhow, Context, Error, Result};
-use clap::{CommandFactory, FromArgMatches, Parser};
-use log::{debug, error};
-use sorter::{Sorter, Strategy};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use comparer::{DateOrder, PathOrder, PathType, Timezone};
+use lazy_regex::Regex;
+use log::{debug, error, warn};
+use sorter::{InvalidLinePolicy, Sorter, SorterOptions, Strategy};
 use std::{
-    collections::hash_map::DefaultHasher,
+    collections::{hash_map::DefaultHasher, HashSet},
     env::args_os,
     ffi::OsString,
     fs::{copy, File},
@@ -31,7 +34,8 @@ const MAX_TERM_WIDTH: usize = 100;
 #[clap(after_long_help = long_help())]
 #[allow(clippy::struct_excessive_bools)]
 struct Cli {
-    /// The type of sorting to use.
+    /// The type of sorting to use. Use "auto" to pick a sorting method based
+    /// on the file's contents.
     #[arg(short, long, value_enum)]
     sort: Strategy,
     /// The locale to use for sorting. If this is not specified the sorting is in codepoint order.
@@ -58,9 +62,120 @@ struct Cli {
     /// Sort in reverse order.
     #[arg(short, long)]
     reverse: bool,
-    /// Parse paths as Windows paths for path sort.
-    #[arg(long)]
+    /// Parse paths as Windows paths for path sort. This is the same as
+    /// passing "--path-type windows".
+    #[arg(long, conflicts_with = "path_type")]
     windows: bool,
+    /// How to parse paths for path sort. The default is "unix".
+    #[arg(long, value_enum, value_name = "TYPE")]
+    path_type: Option<PathType>,
+    /// A chrono strftime format for the datetime at the start of each line
+    /// for datetime-text sort, like "%d/%m/%Y %H:%M:%S". This can be passed
+    /// more than once, and the formats are tried in the order given.
+    #[arg(long, value_name = "FORMAT")]
+    datetime_format: Vec<String>,
+    /// The timezone for datetimes without an offset for datetime-text sort.
+    /// This can be an IANA timezone name, like "America/Chicago", or an
+    /// offset from UTC, like "+05:30". The default is UTC.
+    #[arg(long, value_name = "TZ")]
+    timezone: Option<Timezone>,
+    /// Parse numeric dates like "01/02/2026" with the day first for
+    /// datetime-text sort.
+    #[arg(long, conflicts_with_all = ["month_first", "datetime_format"])]
+    day_first: bool,
+    /// Parse numeric dates like "01/02/2026" with the month first for
+    /// datetime-text sort.
+    #[arg(long, conflicts_with = "datetime_format")]
+    month_first: bool,
+    /// How to order paths for path sort. The default is "depth".
+    #[arg(long, value_enum, value_name = "ORDER")]
+    path_order: Option<PathOrder>,
+    /// Sort directories before files at the same level for path sort.
+    /// Directories are paths with a trailing separator, like "src/", as well
+    /// as the directories that other paths are in.
+    #[arg(long)]
+    dirs_first: bool,
+    /// Compare numbers in path components by their numeric value for path
+    /// sort, so file2.txt comes before file10.txt and v1.9 comes before
+    /// v1.10.
+    #[arg(long)]
+    natural: bool,
+    /// Normalize paths before comparing them for path sort, without looking
+    /// at the filesystem. This ignores repeated separators, trailing
+    /// separators, and "." components, and resolves ".." components, so
+    /// a/./b, a//b, a/b/, and a/c/../b are all the same path for --unique.
+    /// Windows paths are also compared case-insensitively, and with
+    /// --path-type mixed, so is every other path. With --canonicalize, the
+    /// lines are rewritten in their normalized form.
+    #[arg(long)]
+    normalize: bool,
+    /// Remove every path which does not exist when sorting by path. Each
+    /// line is resolved relative to the directory containing the file, or
+    /// the directory given by --root. A line containing a glob pattern is
+    /// removed if the pattern doesn't match anything.
+    #[arg(long, conflicts_with_all = ["check", "merge"])]
+    prune_missing: bool,
+    /// When used with --check, also check that every path exists when
+    /// sorting by path. Every path which does not exist is listed. Paths are
+    /// resolved the same way as with --prune-missing.
+    #[arg(long, requires = "check", conflicts_with = "merge")]
+    check_exists: bool,
+    /// The directory to resolve paths relative to with --prune-missing or
+    /// --check-exists. A path starting with a separator is also resolved
+    /// relative to this directory. This defaults to the directory containing
+    /// the file.
+    #[arg(long, value_name = "DIR")]
+    root: Option<PathBuf>,
+    /// Indent each path by two spaces for each earlier path that contains it
+    /// when sorting paths in tree order. Since this changes the lines, it
+    /// can only be used with --stdout.
+    #[arg(long, requires = "stdout", conflicts_with_all = ["merge", "reverse"])]
+    indent: bool,
+    /// Treat some lines as continuations of the line before them, like the
+    /// lines of a stack trace in a log file. Each line is sorted along with
+    /// all of its continuation lines. This cannot be used with --unique,
+    /// since records with the same first line may not be duplicates.
+    #[arg(long, value_enum, value_name = "MODE", conflicts_with = "unique")]
+    continuation: Option<ContinuationPolicy>,
+    /// The regex that continuation lines match when --continuation is
+    /// "matching".
+    #[arg(
+        long,
+        value_name = "REGEX",
+        requires = "continuation",
+        required_if_eq("continuation", "matching")
+    )]
+    continuation_regex: Option<Regex>,
+    /// What to do with lines that cannot be parsed when sorting by ip,
+    /// endpoint, network, domain, email, or url. The default is "error".
+    #[arg(long, value_enum, value_name = "HANDLING")]
+    invalid: Option<InvalidLinePolicy>,
+    /// Treat IPv4-mapped IPv6 addresses as the IPv4 address they map to when
+    /// sorting by ip or endpoint.
+    #[arg(long)]
+    map_ipv4: bool,
+    /// Rewrite each line into its canonical form when sorting by path, ip,
+    /// endpoint, network, domain, email, or url. With --unique, lines are
+    /// deduplicated after they are rewritten. With --check, a line that is
+    /// not in canonical form is treated as an error.
+    #[arg(long)]
+    canonicalize: bool,
+    /// Remove every network that is contained in another network when
+    /// sorting by network. The comments for removed networks are added to
+    /// the network that contains them. With --check, a network that can be
+    /// removed is treated as an error.
+    #[arg(long, conflicts_with = "merge")]
+    collapse: bool,
+    /// Like --collapse, but also merge adjacent networks into the smallest
+    /// set of networks that covers them, so 10.0.0.0/25 and 10.0.0.128/25
+    /// become 10.0.0.0/24.
+    #[arg(long, conflicts_with = "merge")]
+    aggregate: bool,
+    /// When used with --check, also check that no network overlaps another
+    /// network when sorting by network. Every overlapping network is listed
+    /// along with the network it overlaps.
+    #[arg(long, requires = "check")]
+    no_overlap: bool,
     /// Modify the file in place instead of making a backup.
     #[arg(short, long, group = "output")]
     in_place: bool,
@@ -71,11 +186,81 @@ struct Cli {
     /// sorted (or not unique if --unique is given) the exit status will be 1.
     #[arg(long, group = "output")]
     check: bool,
-    /// The file to sort.
-    file: PathBuf,
+    /// Check that every line in the file can be parsed by the sorting method
+    /// instead of sorting it. This ignores the order of the lines. Every line
+    /// that cannot be parsed is listed, and the exit status will be 1. This
+    /// is stricter than sorting, so for example a network with host bits set
+    /// or a line without a leading datetime is reported as invalid.
+    #[arg(long, group = "output", conflicts_with = "merge")]
+    validate: bool,
+    /// Merge two or more files that are already sorted instead of sorting a
+    /// single file. Each file is checked to make sure it is sorted before it
+    /// is merged. The merged output is always printed to stdout. Lines that
+    /// compare as equal are output in the order the files were given. When
+    /// used with --check this just checks that every file is sorted.
+    #[arg(short, long, conflicts_with = "in_place")]
+    merge: bool,
+    /// The file to sort. When --merge is given, this is the list of files to
+    /// merge.
+    #[arg(value_name = "FILE")]
+    files: Vec<PathBuf>,
     /// Print debugging info while running.
     #[arg(long)]
     debug: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Insert one or more lines into a file that is already sorted. Each line
+    /// is inserted at the position where sorting would put it. If the
+    /// --unique flag is set then lines which are already in the file are
+    /// skipped with a warning.
+    Add {
+        /// The comment to add before each line. Comments require the
+        /// --comment-prefix flag. If the comment does not start with the
+        /// comment prefix, the prefix will be added. You can pass this once
+        /// per line, in which case the comments are matched with the lines
+        /// in order.
+        #[arg(long, value_name = "COMMENT")]
+        comment: Vec<String>,
+        /// The sorted file to add lines to.
+        file: PathBuf,
+        /// The lines to add.
+        #[arg(required = true)]
+        lines: Vec<String>,
+    },
+    /// Remove one or more lines from a file that is already sorted. Any
+    /// comment attached to a removed line is removed as well.
+    Remove {
+        /// The sorted file to remove lines from.
+        file: PathBuf,
+        /// The lines to remove.
+        #[arg(required = true)]
+        lines: Vec<String>,
+    },
+    /// Look up one or more values in a file that is already sorted using a
+    /// binary search, and print the matching lines. By default this looks
+    /// for lines which compare as equal to each value. The file is not
+    /// checked to make sure it is sorted, so if it is not the results will be
+    /// wrong. If any value is not found the exit status will be 1.
+    Lookup {
+        /// Print every line starting with each value. This is only allowed
+        /// when sorting by text without a locale.
+        #[arg(long, conflicts_with = "within")]
+        prefix: bool,
+        /// Treat each value as a network and print every address or network
+        /// in the file which is contained in that network. This is only
+        /// allowed when sorting by ip, endpoint, or network.
+        #[arg(long)]
+        within: bool,
+        /// The sorted file to look in.
+        file: PathBuf,
+        /// The values to look up.
+        #[arg(required = true)]
+        values: Vec<String>,
+    },
 }
 
 fn main() {
@@ -97,6 +282,12 @@ fn long_help() -> String {
     const HELP: &str = r#"
 There are a number of different sorting methods available.
 
+## Auto (`--sort auto`)
+
+This looks at a sample of up to about 1,000 lines from the file and picks one of the other sorting methods based on how many of those lines can be parsed by each method. The methods are tried in this order: `ip`, `network`, `endpoint`, `url`, `email`, `domain`, `ip-text`, `datetime-text`, `numbered-text`, `path`, and finally `text`. The `ip`, `network`, `endpoint`, `url`, `email`, and `domain` methods are only picked if every sampled line parses. For `url`, this means that every URL has a host, and for `domain`, every domain has at least two labels, so a list of single words is sorted as text. The others are picked if at least 90% of the sampled lines parse. For `path`, this means that the line contains a path separator.
+
+Pass the `--debug` flag to see which method was picked. When used with `--check`, this also checks every line in the file and fails if that picks a different method than the sample did.
+
 ## Text (`--sort text`)
 
 This sorts each line of the file as text without any special parsing. The exact sorting is determined by the `--locale`, `--case-insensitive`, and `--reverse` flags.
@@ -115,11 +306,21 @@ This sorting method accepts the `--locale`, `--case-insensitive`, and `--reverse
 
 ## Datetime (`--sort datetime-text`)
 
-This sorting method assumes that each line starts with a date or datetime, without any space in it. That means that a string with both a date *and* a time needs to be in a format like "2019-08-27T19:13:16".
+This sorting method looks for a date or datetime at the start of each line. This can be a datetime without any spaces in it, like "2019-08-27T19:13:16", or a date followed by a time, like "2019-08-27 19:13:16" or "2019-08-27 19:13:16.123 +0200". Syslog timestamps like "Oct 17 12:00:01" are also recognized. Since these don't include a year, they are treated as being in the current year.
 
-Lines should not have any leading space before the datetime.
+If a line doesn't start with a datetime, the first text in square brackets is used instead, if it is a datetime. This handles Common Log Format lines like `127.0.0.1 - - [17/Oct/2026:12:00:01 +0000] "GET / HTTP/1.1" 200 512`.
 
-This sorting method accepts the `--locale`, `--case-insensitive`, and `--reverse` flags.
+Lines should not have any leading space before the datetime. Lines with the same datetime are sorted as text, and lines without a datetime are sorted as text after all of the lines with one.
+
+If your datetimes are in a format that isn't recognized, or one that is ambiguous, you can pass one or more `--datetime-format` options with a chrono strftime format, like `%d/%m/%Y` or `%s` for Unix epoch seconds. The formats are tried in the order they are given, and the first one that matches the start of the line is used. The datetime must be followed by whitespace or the end of the line. A format without a time is treated as midnight, and a format without an offset is treated as UTC. When you pass this option, only these formats are used, so you can use `--validate` to list every line that doesn't start with a datetime in one of them.
+
+Datetimes without an offset from UTC are treated as UTC. You can pass the `--timezone` option to use a different timezone for these, either as an IANA timezone name like `America/Chicago` or as an offset like `+05:30`. With a timezone name, daylight saving time is taken into account. A date without a time is treated as midnight in that timezone.
+
+Numeric dates like `01/02/2026` are parsed with the month first by default. If you pass the `--day-first` flag, they are parsed with the day first instead, so this is the 1st of February. You can also pass `--month-first` to be explicit about this. With either flag, the parts of the date can be separated by `/`, `.`, or `-`. These flags cannot be combined with `--datetime-format`, since a format already says which part is which.
+
+When sorting log files, a record can span more than one line, like an error followed by a stack trace. You can pass the `--continuation` option to keep these lines with the line before them, so each record is sorted as a unit based on its first line. With `--continuation indented`, lines that start with whitespace are continuation lines. With `--continuation matching`, lines that match the regex given by `--continuation-regex` are continuation lines. With `--continuation unparsed`, lines that the sorting method can't parse are continuation lines, so for this sorting method that is every line without a datetime. This option works with other sorting methods too, except that `--continuation unparsed` can't be used when sorting by `text` or `path`, since those accept every line. It can't be combined with `--unique`, since two records with the same first line aren't necessarily duplicates.
+
+This sorting method accepts the `--locale`, `--case-insensitive`, `--reverse`, `--datetime-format`, `--timezone`, `--day-first`, and `--month-first` flags.
 
 ## Path (`--sort path`)
 
@@ -128,26 +329,81 @@ Each line is treated as a path.
 The paths are sorted by the following rules:
 
 * Absolute paths come before relative.
-* Paths are sorted by depth before sorting by the path content, so /z comes before /a/a.
+* By default, paths are sorted by depth before sorting by the path content, so /z comes before /a/a. If you pass `--path-order tree`, paths are sorted in tree order instead, like the output of `find`, so each directory is followed by everything under it and /a/a comes before /z.
+* If you pass `--path-order extension`, paths are grouped by their file extension first, and paths without an extension come first. If you pass `--path-order basename`, paths are sorted by their last component first, so paths with the same file name are grouped together and sorted by their parent directory, compared one component at a time. Paths with the same extension are sorted by depth.
+* If you pass the `--natural` flag, numbers in each path component are compared by their numeric value, so `file2.txt` comes before `file10.txt` and `v1.9` comes before `v1.10`. Numbers with leading zeros, like `02`, are equal to the same number without them, and these are then sorted as text.
+* If you pass the `--normalize` flag, paths are normalized before they are compared, without looking at the filesystem. Repeated separators, trailing separators, and `.` components are ignored, and each `..` component removes the component before it, so `a/./b`, `a//b`, `a/b/`, and `a/c/../b` are all the same path. A `..` at the start of a relative path is kept. Windows paths are also compared case-insensitively. With `--path-type mixed`, every path is sorted case-insensitively, but only Windows paths that differ in case are the same path. With `--unique`, only the first of a set of equivalent paths is kept, and with `--canonicalize`, each path is rewritten in its normalized form.
+* If you pass the `--dirs-first` flag, directories sort before files at the same level. Directories are paths with a trailing separator, like `src/`, as well as the directories that other paths are in. With `--canonicalize`, the trailing separator is kept.
 * If you pass the `--windows` flag, then paths with drive letters or UNC names are sorted based on that prefix first. Paths with drive letters or UNC names sort before paths without them.
+* If you pass `--path-type mixed`, each path is parsed as either a Windows or Unix path. A path is a Windows path if it starts with a drive letter or UNC name, or if it contains a backslash but no forward slash. Paths with drive letters or UNC names come first and are sorted as Windows paths. Every other path is sorted as if it were a Unix path, and the backslashes in a Windows path are treated as forward slashes. This means that absolute paths, like `/usr/local` and `\Windows`, come next, followed by relative paths. A path with both kinds of separator is a Unix path, so its backslashes are part of a file name.
+
+This sorting method accepts the `--locale`, `--case-insensitive`, and `--reverse` flags in addition to the `--windows`, `--path-type`, `--path-order`, `--dirs-first`, `--natural`, and `--normalize` flags.
 
-This sorting method accepts the `--locale`, `--case-insensitive`, and `--reverse` flags in addition to the `--windows` flag.
+If you pass the `--prune-missing` flag, every path which does not exist is removed from the file. With `--check`, you can pass the `--check-exists` flag to report every path which does not exist instead. Each path is resolved relative to the directory containing the file, or the directory given by `--root`. A path which starts with a separator is also resolved relative to that directory, like the paths in a `.gitignore` or `CODEOWNERS` file, so to check absolute paths you can pass `--root /`. A path containing a glob pattern, like `src/**/*.rs`, is only treated as missing if there is no path with that exact name and the pattern doesn't match anything, so a file named `pages/[id].tsx` is found. A path which is not a valid glob pattern, like `src/[.rs`, is reported along with the reason the pattern is not valid.
+
+When sorting in tree order, you can also pass the `--indent` flag along with `--stdout` to indent each path by two spaces for each path before it that contains it. This makes the output easier to read, but it is no longer a sorted list of paths, so this cannot be used when writing the file.
 
 ## IP (`--sort ip`)
 
 This method assumes that each line is an IPv4 or IPv6 address (not a network).
 
-The sorting method is the same as if each line were the corresponding integer for the address. IPv4 addresses always sort before IPv6 addresses.
+The sorting method is the same as if each line were the corresponding integer for the address. IPv4 addresses always sort before IPv6 addresses. IPv4-mapped IPv6 addresses like `::ffff:192.0.2.1` are IPv6 addresses unless you pass the `--map-ipv4` flag.
+
+This sorting method accepts the `--reverse`, `--invalid`, and `--map-ipv4` flags. By default a line that cannot be parsed is an error. See the `--invalid` flag for other options.
+
+## Endpoint (`--sort endpoint`)
+
+This method assumes that each line is an IPv4 or IPv6 address, optionally with a zone and a port. It accepts lines like `192.0.2.1`, `192.0.2.1:443`, `fe80::1%eth0`, and `[2001:db8::1]:443`. An IPv6 address must be in brackets in order to have a port.
+
+The lines are sorted by address, then by zone, then by port. The addresses are sorted the same way as the `ip` method sorts them. An address without a zone or port sorts before the same address with one.
 
-This sorting method accepts the `--reverse` flag.
+This sorting method accepts the `--reverse`, `--invalid`, and `--map-ipv4` flags. By default a line that cannot be parsed is an error. See the `--invalid` flag for other options.
+
+## IP Text (`--sort ip-text`)
+
+This assumes that each line of the file starts with an IPv4 or IPv6 address, optionally followed by whitespace and then text. This is the format used by files like `/etc/hosts`.
+
+The lines will be sorted by IP address first. If two lines have the same address they will be sorted by the text after the address, ignoring the whitespace between the address and the text.
+
+Lines without an address always sort after lines with one.
+
+This sorting method accepts the `--locale`, `--case-insensitive`, and `--reverse` flags.
 
 ## Network (`--sort network`)
 
-This method assumes that each line is an IPv4 or IPv6 network in CIDR notation.
+This method assumes that each line is an IPv4 or IPv6 network. A network can be written in CIDR notation (`192.0.2.0/24`), as a single address (`192.0.2.7`), as a range of addresses (`192.0.2.10-192.0.2.20`), or as an IPv4 address followed by a netmask (`192.0.2.0 255.255.255.0`). A single address is treated as a /32 or /128 network.
+
+Networks are sorted by their first address. If there are two networks with the same first address they are sorted with the larger network first (so 1.1.1.0/24 comes before 1.1.1.0/28). IPv4 networks always sort before IPv6 networks.
+
+This sorting method accepts the `--reverse` and `--invalid` flags. By default a line that cannot be parsed is an error. See the `--invalid` flag for other options.
+
+Use the `--collapse` flag to remove networks that are contained in other networks, or the `--aggregate` flag to also merge adjacent networks. Only networks that can be written in CIDR notation are merged by `--aggregate`. Use the `--no-overlap` flag with `--check` to check that no network overlaps another.
+
+## Domain (`--sort domain`)
+
+This method assumes that each line is a domain name, like `www.example.com`.
+
+Domains are compared label by label, starting from the TLD, so `a.example.com` sorts next to `b.example.com` instead of next to `a.example.org`. A domain sorts before its subdomains. The comparison is always case-insensitive, and internationalized domain names are compared in their punycode form, so `bücher.example` and `xn--bcher-kva.example` are the same domain. A single trailing `.` is ignored.
 
-If there are two networks with the same base address they are sorted with the larger network first (so 1.1.1.0/24 comes before 1.1.1.0/28). IPv4 networks always sort before IPv6 networks.
+A domain can start with a `*.` wildcard. A wildcard domain sorts after the domain it is a wildcard for and before that domain's subdomains.
 
-This sorting method accepts the `--reverse` flag.
+This sorting method accepts the `--reverse`, `--invalid`, and `--canonicalize` flags. The canonical form of a domain is lowercase punycode without a trailing `.`.
+
+## Email (`--sort email`)
+
+This method assumes that each line is an email address, either bare (`user@example.com`) or with a display name (`Some User <user@example.com>`).
+
+Addresses are sorted by their domain first, using the same ordering as the `domain` method, so addresses are grouped by organization. Addresses with the same domain are sorted by their local part, the part before the `@`. The domain is compared case-insensitively, but the local part is not. If two lines have the same address they are sorted as text.
+
+This sorting method accepts the `--reverse`, `--invalid`, and `--canonicalize` flags. The canonical form of an address has its domain in lowercase punycode.
+
+## URL (`--sort url`)
+
+This method assumes that each line is an absolute URL, like `https://www.example.com/a/b?c=d`.
+
+URLs are sorted by their host first. URLs without a host, like `mailto:` URLs, come first, followed by URLs with an IP address host, and then URLs with a domain host, which are sorted the same way as the `domain` method. URLs with the same host are sorted by scheme, then by port, then by path, and then by query. An explicit default port, like `:443` for `https`, is the same as no port. Paths are compared the same way as the `path` method compares Unix paths. If two URLs are the same up to their query they are sorted by their canonical form, and then as text.
+
+This sorting method accepts the `--reverse`, `--invalid`, and `--canonicalize` flags. The canonical form of a URL has a lowercase scheme and host, no default port, and no trailing slash at the end of its path. With `--unique`, URLs with the same canonical form are duplicates, even without `--canonicalize`.
 "#;
 
     let skin = MadSkin::default();
@@ -176,12 +432,24 @@ impl Cli {
         }
 
         if let Err(e) = self.execute() {
-            error!("{e}");
+            error!("{e:#}");
+            if e.downcast_ref::<ArgsError>().is_some() {
+                return 101;
+            }
+            if e.downcast_ref::<LookupError>().is_some() {
+                return 1;
+            }
             let status = match e.downcast::<CheckError>() {
                 Ok(
                     CheckError::HasUnexpectedEmptyLines
                     | CheckError::NotSorted { .. }
-                    | CheckError::NotUnique { .. },
+                    | CheckError::NotUnique { .. }
+                    | CheckError::UnstableStrategy { .. }
+                    | CheckError::HasInvalidLines { .. }
+                    | CheckError::NotCanonical { .. }
+                    | CheckError::NotCollapsed { .. }
+                    | CheckError::HasOverlappingNetworks { .. }
+                    | CheckError::HasMissingPaths { .. },
                 ) => 1,
                 _ => 2,
             };
@@ -192,52 +460,370 @@ impl Cli {
     }
 
     fn validate_args(&self) -> Result<()> {
-        if self.locale.is_some() && !self.sort.supports_locale() {
+        // When the strategy is auto-detected, these checks are done after
+        // detection instead.
+        if !matches!(self.sort, Strategy::Auto) {
+            self.validate_strategy_args(self.sort)?;
+        }
+
+        if self.in_place && self.check {
+            return Err(anyhow!("you cannot set both --in-place and --stdout"));
+        }
+
+        if self.files.is_empty() && self.command.is_none() {
+            return Err(anyhow!("you must pass a file to sort"));
+        }
+
+        if self.files.len() > 1 && !self.merge {
             return Err(anyhow!(
-                "you cannot set a locale when sorting by {:?}",
-                self.sort,
+                "you can only pass more than one file when using the --merge flag"
             ));
         }
 
-        if self.windows && !self.sort.supports_path_type() {
+        if let Some(command) = &self.command {
+            if !self.files.is_empty() {
+                return Err(anyhow!(
+                    "you cannot pass a file before the {} subcommand",
+                    command.name(),
+                ));
+            }
+            if self.check || self.merge || self.validate {
+                return Err(anyhow!(
+                    "you cannot pass the --check, --merge, or --validate flags with the {} subcommand",
+                    command.name(),
+                ));
+            }
+            if self.indent || self.prune_missing {
+                return Err(anyhow!(
+                    "you cannot pass the --indent or --prune-missing flags with the {} subcommand",
+                    command.name(),
+                ));
+            }
+            if self.continuation.is_some() {
+                return Err(anyhow!(
+                    "you cannot pass the --continuation flag with the {} subcommand",
+                    command.name(),
+                ));
+            }
+        }
+
+        if self.continuation_regex.is_some()
+            && self.continuation != Some(ContinuationPolicy::Matching)
+        {
             return Err(anyhow!(
-                "you cannot pass the --windows flag when sorting {:?}",
-                self.sort,
+                "you can only pass the --continuation-regex flag with --continuation matching"
             ));
         }
 
-        if self.in_place && self.check {
-            return Err(anyhow!("you cannot set both --in-place and --stdout"));
+        if self.root.is_some() && !self.prune_missing && !self.check_exists {
+            return Err(anyhow!(
+                "you can only pass the --root flag with --prune-missing or --check-exists"
+            ));
+        }
+
+        if let Some(Command::Add { comment, lines, .. }) = &self.command {
+            if self.collapse || self.aggregate {
+                return Err(anyhow!(
+                    "you cannot pass the --collapse or --aggregate flags with the add subcommand"
+                ));
+            }
+            if !comment.is_empty() && self.comment_prefix.is_none() {
+                return Err(anyhow!(
+                    "you must set --comment-prefix in order to add comments"
+                ));
+            }
+            if comment.len() > 1 && comment.len() != lines.len() {
+                return Err(anyhow!(
+                    "you passed {} comments for {} lines, you must pass one comment or one per line",
+                    comment.len(),
+                    lines.len(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_strategy_args(&self, strategy: Strategy) -> Result<()> {
+        if self.locale.is_some() && !strategy.supports_locale() {
+            return Err(anyhow!(
+                "you cannot set a locale when sorting by {strategy:?}",
+            ));
+        }
+
+        if (self.windows || self.path_type.is_some()) && !strategy.supports_path_type() {
+            return Err(anyhow!(
+                "you cannot pass the --windows or --path-type flags when sorting {strategy:?}",
+            ));
+        }
+
+        if self.path_order.is_some() && !strategy.supports_path_order() {
+            return Err(anyhow!(
+                "you cannot pass the --path-order flag when sorting {strategy:?}",
+            ));
+        }
+
+        if self.dirs_first && !strategy.supports_path_order() {
+            return Err(anyhow!(
+                "you cannot pass the --dirs-first flag when sorting {strategy:?}",
+            ));
+        }
+
+        if self.natural && !strategy.supports_path_order() {
+            return Err(anyhow!(
+                "you cannot pass the --natural flag when sorting {strategy:?}",
+            ));
+        }
+
+        if self.normalize && !strategy.supports_path_order() {
+            return Err(anyhow!(
+                "you cannot pass the --normalize flag when sorting {strategy:?}",
+            ));
+        }
+
+        if (self.prune_missing || self.check_exists) && !strategy.supports_path_type() {
+            return Err(anyhow!(
+                "you cannot pass the --prune-missing or --check-exists flags when sorting {strategy:?}",
+            ));
+        }
+
+        if self.indent && self.path_order != Some(PathOrder::Tree) {
+            return Err(anyhow!(
+                "you can only pass the --indent flag when sorting paths with --path-order tree",
+            ));
+        }
+
+        if !self.datetime_format.is_empty() && !strategy.supports_datetime_format() {
+            return Err(anyhow!(
+                "you cannot pass the --datetime-format flag when sorting {strategy:?}",
+            ));
+        }
+
+        if self.timezone.is_some() && !strategy.supports_datetime_format() {
+            return Err(anyhow!(
+                "you cannot pass the --timezone flag when sorting {strategy:?}",
+            ));
+        }
+
+        if (self.day_first || self.month_first) && !strategy.supports_datetime_format() {
+            return Err(anyhow!(
+                "you cannot pass the --day-first or --month-first flags when sorting {strategy:?}",
+            ));
+        }
+
+        if self.continuation == Some(ContinuationPolicy::Unparsed)
+            && !strategy.supports_unparsed_continuation()
+        {
+            return Err(anyhow!(
+                "you cannot pass --continuation unparsed when sorting {strategy:?}, since every line can be parsed",
+            ));
+        }
+
+        if self.invalid.is_some() && !strategy.requires_valid_lines() {
+            return Err(anyhow!(
+                "you cannot pass the --invalid flag when sorting {strategy:?}",
+            ));
+        }
+
+        if self.canonicalize && !strategy.supports_canonicalize() {
+            return Err(anyhow!(
+                "you cannot pass the --canonicalize flag when sorting {strategy:?}",
+            ));
+        }
+
+        if (self.collapse || self.aggregate) && !strategy.supports_collapse() {
+            return Err(anyhow!(
+                "you cannot pass the --collapse or --aggregate flags when sorting {strategy:?}",
+            ));
+        }
+
+        if self.map_ipv4 && !strategy.supports_ipv4_mapping() {
+            return Err(anyhow!(
+                "you cannot pass the --map-ipv4 flag when sorting {strategy:?}",
+            ));
+        }
+
+        if self.no_overlap && !strategy.supports_overlap_check() {
+            return Err(anyhow!(
+                "you cannot pass the --no-overlap flag when sorting {strategy:?}",
+            ));
+        }
+
+        if let Some(Command::Lookup { prefix, within, .. }) = &self.command {
+            if *prefix && (!matches!(strategy, Strategy::Text) || self.locale.is_some()) {
+                return Err(anyhow!(
+                    "you can only use the --prefix flag when sorting by text without a locale"
+                ));
+            }
+            if *within && !strategy.supports_network_lookup() {
+                return Err(anyhow!(
+                    "you cannot use the --within flag when sorting {strategy:?}",
+                ));
+            }
         }
 
         Ok(())
     }
 
     fn execute(&self) -> Result<()> {
-        let sorter = Sorter::new(
-            self.sort,
-            self.locale.as_deref(),
-            self.unique,
-            self.case_insensitive,
-            self.reverse,
-            self.windows,
-        )?;
+        match &self.command {
+            Some(Command::Add {
+                comment,
+                file,
+                lines,
+            }) => return self.add_lines(file, lines, comment),
+            Some(Command::Remove { file, lines }) => return self.remove_lines(file, lines),
+            Some(Command::Lookup {
+                prefix,
+                within,
+                file,
+                values,
+            }) => return self.lookup_values(file, values, *prefix, *within),
+            None => (),
+        }
+
+        if self.merge {
+            return self.merge_files();
+        }
+
+        let file = &self.files[0];
         let (lines, has_empty_lines, line_ending) =
-            read_lines(&self.file, self.comment_prefix.as_deref())?;
+            read_lines(file, self.comment_prefix.as_deref())?;
+        let sorter = self.sorter(&lines)?;
+        let lines = self.group_continuations(lines, &sorter);
+        if self.validate {
+            let invalid_lines = sorter.validate_lines(&lines);
+            if !invalid_lines.is_empty() {
+                return Err(CheckError::HasInvalidLines {
+                    lines: invalid_lines,
+                }
+                .into());
+            }
+            return Ok(());
+        }
+
         if self.check {
             if has_empty_lines {
                 return Err(CheckError::HasUnexpectedEmptyLines.into());
             }
+            if self.check_exists {
+                let missing = self.missing_paths(file, &lines);
+                if !missing.is_empty() {
+                    return Err(CheckError::HasMissingPaths { lines: missing }.into());
+                }
+            }
             if sorter.lines_are_sorted(&lines)? {
                 return Ok(());
             }
         }
 
-        self.sort_lines(lines, has_empty_lines, line_ending, &sorter)
+        self.sort_lines(file, lines, has_empty_lines, line_ending, &sorter)
+    }
+
+    fn sorter<'a, I>(&self, lines: I) -> Result<Sorter>
+    where
+        I: IntoIterator<Item = &'a SortableLine>,
+    {
+        let strategy = if matches!(self.sort, Strategy::Auto) {
+            let lines = lines
+                .into_iter()
+                .map(|l| l.line.as_str())
+                .collect::<Vec<_>>();
+            let strategy = Strategy::detect(&lines, true);
+            debug!("auto-detected the {strategy:?} sorting strategy");
+            if self.check {
+                let full = Strategy::detect(&lines, false);
+                if full != strategy {
+                    return Err(CheckError::UnstableStrategy {
+                        sampled: strategy,
+                        full,
+                    }
+                    .into());
+                }
+            }
+            self.validate_strategy_args(strategy).map_err(|e| {
+                ArgsError::InvalidForDetectedStrategy {
+                    message: e.to_string(),
+                }
+            })?;
+            strategy
+        } else {
+            self.sort
+        };
+
+        Sorter::new(
+            strategy,
+            &SorterOptions {
+                locale: self.locale.as_deref(),
+                unique: self.unique,
+                case_insensitive: self.case_insensitive,
+                reverse: self.reverse,
+                path_type: self.path_type(),
+                path_order: self.path_order.unwrap_or_default(),
+                dirs_first: self.dirs_first,
+                natural: self.natural,
+                normalize: self.normalize,
+                indent: self.indent,
+                datetime_formats: &self.datetime_format,
+                timezone: self.timezone.unwrap_or_default(),
+                date_order: if self.day_first {
+                    DateOrder::DayFirst
+                } else if self.month_first {
+                    DateOrder::MonthFirst
+                } else {
+                    DateOrder::Auto
+                },
+                invalid: self.invalid.unwrap_or_default(),
+                canonicalize: self.canonicalize,
+                collapse: self.collapse,
+                aggregate: self.aggregate,
+                no_overlap: self.no_overlap,
+                map_ipv4: self.map_ipv4,
+            },
+        )
+    }
+
+    fn merge_files(&self) -> Result<()> {
+        let mut inputs = vec![];
+        let mut merged_line_ending = None;
+        for file in &self.files {
+            let (lines, has_empty_lines, line_ending) =
+                read_lines(file, self.comment_prefix.as_deref())?;
+            if self.check && has_empty_lines {
+                return Err(Error::from(CheckError::HasUnexpectedEmptyLines)
+                    .context(format!("cannot merge {}", file.display())));
+            }
+
+            // The output uses the line ending of the first file.
+            merged_line_ending.get_or_insert(line_ending);
+            inputs.push((file, lines));
+        }
+
+        let sorter = self.sorter(inputs.iter().flat_map(|(_, lines)| lines))?;
+        let inputs = inputs
+            .into_iter()
+            .map(|(file, lines)| (file, self.group_continuations(lines, &sorter)))
+            .collect::<Vec<_>>();
+        for (file, lines) in &inputs {
+            sorter
+                .lines_are_sorted(lines)
+                .with_context(|| format!("cannot merge {}", file.display()))?;
+        }
+
+        if self.check {
+            return Ok(());
+        }
+
+        write_lines_to_writer(
+            sorter.merge_lines(inputs.into_iter().map(|(_, lines)| lines).collect()),
+            merged_line_ending.unwrap(),
+            &mut stdout(),
+        )
     }
 
     fn sort_lines(
         &self,
+        file: &Path,
         lines: Vec<SortableLine>,
         has_empty_lines: bool,
         line_ending: &'static str,
@@ -248,6 +834,11 @@ impl Cli {
         } else {
             Some(hash_lines(&lines))
         };
+        let lines = if self.prune_missing {
+            self.prune_missing_paths(file, lines)
+        } else {
+            lines
+        };
         let lines = sorter.sort_lines(lines)?;
         if !has_empty_lines {
             let new_hash = hash_lines(&lines);
@@ -257,12 +848,181 @@ impl Cli {
             }
         }
 
+        self.write_lines(file, lines, line_ending)
+    }
+
+    // The sorting method is picked before lines are grouped, so with `--sort
+    // auto` it is picked based on every line, including continuation lines.
+    fn group_continuations(&self, lines: Vec<SortableLine>, sorter: &Sorter) -> Vec<SortableLine> {
+        match self.continuation {
+            None => lines,
+            Some(ContinuationPolicy::Indented) => {
+                group_continuation_lines(lines, |l| l.starts_with(char::is_whitespace))
+            }
+            Some(ContinuationPolicy::Matching) => {
+                let re = self.continuation_regex.as_ref().unwrap();
+                group_continuation_lines(lines, |l| re.is_match(l))
+            }
+            Some(ContinuationPolicy::Unparsed) => {
+                group_continuation_lines(lines, |l| !sorter.is_valid_line(l))
+            }
+        }
+    }
+
+    fn path_type(&self) -> PathType {
+        if self.windows {
+            PathType::Windows
+        } else {
+            self.path_type.unwrap_or_default()
+        }
+    }
+
+    fn missing_paths(&self, file: &Path, lines: &[SortableLine]) -> Vec<InvalidLine> {
+        // The parent of a bare file name is an empty path, which resolves
+        // relative to the current directory.
+        let root = match &self.root {
+            Some(r) => r.as_path(),
+            None => file.parent().unwrap_or_else(|| Path::new("")),
+        };
+        exists::missing_paths(lines, root, self.path_type())
+    }
+
+    fn prune_missing_paths(&self, file: &Path, mut lines: Vec<SortableLine>) -> Vec<SortableLine> {
+        let missing = self.missing_paths(file, &lines);
+        for l in &missing {
+            debug!("pruning missing path - {l}");
+        }
+        let missing = missing
+            .into_iter()
+            .map(|l| l.line_number)
+            .collect::<HashSet<_>>();
+        lines.retain(|l| !missing.contains(&l.line_number));
+        lines
+    }
+
+    fn add_lines(&self, file: &Path, new_lines: &[String], comments: &[String]) -> Result<()> {
+        let (mut lines, _, line_ending) = read_lines(file, self.comment_prefix.as_deref())?;
+        let sorter = self.sorter(&lines)?;
+        sorter.lines_are_sorted(&lines)?;
+
+        let mut changed = false;
+        for (i, new_line) in new_lines.iter().enumerate() {
+            let comment = match comments.len() {
+                0 => None,
+                1 => Some(&comments[0]),
+                _ => Some(&comments[i]),
+            };
+            let comment = comment.map(|c| {
+                // This unwrap is safe because `validate_args` ensures that we
+                // have a prefix when there are comments.
+                let prefix = self.comment_prefix.as_deref().unwrap();
+                Comment {
+                    is_preceded_by_empty_line: false,
+                    lines: vec![if c.starts_with(prefix) {
+                        c.clone()
+                    } else {
+                        format!("{prefix} {c}")
+                    }],
+                }
+            });
+
+            // Added lines don't have a line number in the original file.
+            let line = SortableLine {
+                line_number: 0,
+                line: new_line.clone(),
+                comment,
+                continuation: vec![],
+            };
+            if sorter.add_line(&mut lines, line)? {
+                changed = true;
+            } else {
+                warn!("the line `{new_line}` is already in the file");
+            }
+        }
+
+        if !changed && !self.stdout {
+            debug!("no lines were added to the file");
+            return Ok(());
+        }
+
+        self.write_lines(file, lines, line_ending)
+    }
+
+    fn remove_lines(&self, file: &Path, old_lines: &[String]) -> Result<()> {
+        let (mut lines, _, line_ending) = read_lines(file, self.comment_prefix.as_deref())?;
+        let sorter = self.sorter(&lines)?;
+        sorter.lines_are_sorted(&lines)?;
+
+        let mut changed = false;
+        for old_line in old_lines {
+            if sorter.remove_line(&mut lines, old_line)? == 0 {
+                warn!("the line `{old_line}` is not in the file");
+            } else {
+                changed = true;
+            }
+        }
+
+        if !changed && !self.stdout {
+            debug!("no lines were removed from the file");
+            return Ok(());
+        }
+
+        self.write_lines(file, lines, line_ending)
+    }
+
+    fn lookup_values(
+        &self,
+        file: &Path,
+        values: &[String],
+        prefix: bool,
+        within: bool,
+    ) -> Result<()> {
+        let (lines, _, line_ending) = read_lines(file, self.comment_prefix.as_deref())?;
+        let sorter = self.sorter(&lines)?;
+
+        let mut bw = BufWriter::new(stdout());
+        let mut not_found = vec![];
+        for value in values {
+            let found = if prefix {
+                sorter
+                    .find_with_prefix(&lines, value, self.case_insensitive)?
+                    .iter()
+                    .collect::<Vec<_>>()
+            } else if within {
+                sorter.find_within(&lines, value)?
+            } else {
+                sorter.find_equal(&lines, value)?.iter().collect::<Vec<_>>()
+            };
+
+            if found.is_empty() {
+                not_found.push(value.clone());
+            }
+            for line in found {
+                bw.write_all(line.line.as_bytes())?;
+                bw.write_all(line_ending.as_bytes())?;
+            }
+        }
+        bw.flush()?;
+
+        if !not_found.is_empty() {
+            return Err(LookupError::NotFound { values: not_found }.into());
+        }
+
+        Ok(())
+    }
+
+    fn write_lines(
+        &self,
+        file: &Path,
+        lines: Vec<SortableLine>,
+        line_ending: &'static str,
+    ) -> Result<()> {
         if self.stdout {
-            return write_lines_to_writer(lines, line_ending, &mut stdout());
+            return write_lines_to_writer(lines.into_iter().map(Ok), line_ending, &mut stdout());
         }
 
         if !self.in_place {
-            let mut bak_file = self.file.clone();
+            let mut bak_file = file.to_path_buf();
             let ext = bak_file
                 .extension()
                 .map_or("", |e| e.to_str().unwrap_or(""));
@@ -271,20 +1031,20 @@ impl Cli {
             } else {
                 format!("{ext}.bak")
             });
-            copy(&self.file, bak_file)?;
+            copy(file, bak_file)?;
         }
 
         // If we don't make this in the same directory as the original file,
         // then the `persist` call later may fail because we may end up trying
         // to rename files across filesystems.
-        let mut file = NamedTempFile::new_in(self.file.parent().unwrap())?;
-        write_lines_to_writer(lines, line_ending, &mut file)?;
-        let temp_path = file.path().to_path_buf();
-        file.persist(&self.file).with_context(|| {
+        let mut temp_file = NamedTempFile::new_in(file.parent().unwrap())?;
+        write_lines_to_writer(lines.into_iter().map(Ok), line_ending, &mut temp_file)?;
+        let temp_path = temp_file.path().to_path_buf();
+        temp_file.persist(file).with_context(|| {
             format!(
                 "error renaming {} to {}",
                 temp_path.display(),
-                self.file.display(),
+                file.display(),
             )
         })?;
 
@@ -292,11 +1052,36 @@ impl Cli {
     }
 }
 
+impl Command {
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Add { .. } => "add",
+            Command::Remove { .. } => "remove",
+            Command::Lookup { .. } => "lookup",
+        }
+    }
+}
+
+// This determines which lines are continuations of the line before them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum ContinuationPolicy {
+    /// Lines that start with whitespace are continuation lines.
+    Indented,
+    /// Lines that match --continuation-regex are continuation lines.
+    Matching,
+    /// Lines that the sorting method cannot parse, like lines without a
+    /// leading datetime for datetime-text, are continuation lines.
+    Unparsed,
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub(crate) struct SortableLine {
     line_number: usize,
     line: String,
     comment: Option<Comment>,
+    // These are the lines after this one that belong to the same record.
+    // They're written after the line and move with it when sorting.
+    continuation: Vec<String>,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -306,6 +1091,18 @@ struct Comment {
 }
 
 impl SortableLine {
+    // This adds the other line's comment after this line's comment. This is
+    // used when the other line is merged into this one.
+    fn append_comment(&mut self, other: Option<Comment>) {
+        let Some(other) = other else {
+            return;
+        };
+        match &mut self.comment {
+            Some(c) => c.lines.extend(other.lines),
+            None => self.comment = Some(other),
+        }
+    }
+
     // This is only used in tests.
     #[allow(dead_code)]
     fn from_number_and_str(from: (usize, &str)) -> Self {
@@ -313,8 +1110,18 @@ impl SortableLine {
             line_number: from.0,
             line: from.1.to_string(),
             comment: None,
+            continuation: vec![],
         }
     }
+
+    // Returns the lines numbered from 1, like the lines of a file.
+    #[cfg(test)]
+    fn numbered(strs: &[&str]) -> Vec<Self> {
+        strs.iter()
+            .enumerate()
+            .map(|(i, s)| Self::from_number_and_str((i + 1, s)))
+            .collect()
+    }
 }
 
 fn read_lines<P: AsRef<Path>>(
@@ -366,6 +1173,7 @@ fn lines_from_reader<R: Read>(
             line_number: i + 1,
             line,
             comment,
+            continuation: vec![],
         });
         last_line_was_empty = false;
         comment = None;
@@ -373,6 +1181,30 @@ fn lines_from_reader<R: Read>(
     Ok((lines, has_empty_lines))
 }
 
+// This moves each continuation line, along with any comment before it, into
+// the line before it. A continuation line at the start of the file doesn't
+// have a line to belong to, so it's left as a line of its own.
+fn group_continuation_lines<F>(lines: Vec<SortableLine>, is_continuation: F) -> Vec<SortableLine>
+where
+    F: Fn(&str) -> bool,
+{
+    let mut grouped: Vec<SortableLine> = Vec::with_capacity(lines.len());
+    for line in lines {
+        if let Some(last) = grouped.last_mut() {
+            if is_continuation(&line.line) {
+                if let Some(comment) = line.comment {
+                    last.continuation.extend(comment.lines);
+                }
+                last.continuation.push(line.line);
+                last.continuation.extend(line.continuation);
+                continue;
+            }
+        }
+        grouped.push(line);
+    }
+    grouped
+}
+
 // Doing the uniqueness check here lets us avoid iterating over the lines yet
 // another time while still avoiding rewriting an already sorted file.
 fn hash_lines(lines: &[SortableLine]) -> u64 {
@@ -384,13 +1216,14 @@ fn hash_lines(lines: &[SortableLine]) -> u64 {
     hasher.finish()
 }
 
-fn write_lines_to_writer<W: Write>(
-    lines: Vec<SortableLine>,
-    line_ending: &'static str,
-    out: &mut W,
-) -> Result<()> {
+fn write_lines_to_writer<I, W>(lines: I, line_ending: &'static str, out: &mut W) -> Result<()>
+where
+    I: IntoIterator<Item = Result<SortableLine>>,
+    W: Write,
+{
     let mut bw = BufWriter::new(out);
     for (i, l) in lines.into_iter().enumerate() {
+        let l = l?;
         if let Some(comment) = l.comment {
             // If the comment is the first thing in the file we don't preserve
             // its leading empty line.
@@ -404,6 +1237,10 @@ fn write_lines_to_writer<W: Write>(
         }
         bw.write_all(l.line.as_bytes())?;
         bw.write_all(line_ending.as_bytes())?;
+        for line in l.continuation {
+            bw.write_all(line.as_bytes())?;
+            bw.write_all(line_ending.as_bytes())?;
+        }
     }
 
     Ok(())
@@ -413,9 +1250,11 @@ const FIRST_CHUNK_SIZE: usize = 2048;
 
 const LINE_ENDINGS: [&str; 3] = ["\r\n", "\n", "\r"];
 
+const DEFAULT_LINE_ENDING: &str = if cfg!(windows) { "\r\n" } else { "\n" };
+
 type LineEndingChain<'a> = (Chain<Cursor<Vec<u8>>, &'a mut File>, &'static str);
 
-fn determine_line_ending(file: &mut File) -> Result<LineEndingChain> {
+fn determine_line_ending(file: &mut File) -> Result<LineEndingChain<'_>> {
     let mut buf = [0; FIRST_CHUNK_SIZE];
     let read = file.read(&mut buf)?;
 
@@ -425,6 +1264,15 @@ fn determine_line_ending(file: &mut File) -> Result<LineEndingChain> {
         }
     }
 
+    // If we read the whole file and it has no line ending, it's empty or has
+    // a single line, so any line ending will do.
+    if read < FIRST_CHUNK_SIZE {
+        return Ok((
+            Cursor::new(Vec::from(&buf[0..read])).chain(file),
+            DEFAULT_LINE_ENDING,
+        ));
+    }
+
     Err(could_not_determine_line_ending())
 }
 
@@ -447,12 +1295,13 @@ fn buf_contains_str(needle: &str, haystack: &[u8]) -> bool {
 
 #[cfg(test)]
 mod test {
-    use crate::{CheckError, Cli};
+    use crate::{error::ArgsError, CheckError, Cli};
 
     use super::{Comment, SortableLine};
     use anyhow::Result;
+    use clap::CommandFactory;
     use std::{
-        fs::{metadata, read_dir, read_to_string, write, File},
+        fs::{create_dir_all, metadata, read_dir, read_to_string, write, File},
         io::Write,
         path::PathBuf,
     };
@@ -524,31 +1373,37 @@ baz
                         line_number: 1,
                         line: "foo".to_string(),
                         comment: None,
+                        continuation: vec![],
                     },
                     SortableLine {
                         line_number: 2,
                         line: "bar".to_string(),
                         comment: None,
+                        continuation: vec![],
                     },
                     SortableLine {
                         line_number: 3,
                         line: "# comment 1".to_string(),
                         comment: None,
+                        continuation: vec![],
                     },
                     SortableLine {
                         line_number: 4,
                         line: "baz".to_string(),
                         comment: None,
+                        continuation: vec![],
                     },
                     SortableLine {
                         line_number: 6,
                         line: "# comment 2".to_string(),
                         comment: None,
+                        continuation: vec![],
                     },
                     SortableLine {
                         line_number: 7,
                         line: "quux".to_string(),
                         comment: None,
+                        continuation: vec![],
                     },
                 ],
                 true,
@@ -563,11 +1418,13 @@ baz
                         line_number: 1,
                         line: "foo".to_string(),
                         comment: None,
+                        continuation: vec![],
                     },
                     SortableLine {
                         line_number: 2,
                         line: "bar".to_string(),
                         comment: None,
+                        continuation: vec![],
                     },
                     SortableLine {
                         line_number: 4,
@@ -576,6 +1433,7 @@ baz
                             lines: vec!["# comment 1".to_string()],
                             is_preceded_by_empty_line: false,
                         }),
+                        continuation: vec![],
                     },
                     SortableLine {
                         line_number: 7,
@@ -584,6 +1442,7 @@ baz
                             lines: vec!["# comment 2".to_string()],
                             is_preceded_by_empty_line: true,
                         }),
+                        continuation: vec![],
                     },
                 ],
                 false
@@ -616,7 +1475,7 @@ baz
         for t in tests {
             let mut buf = vec![];
             let (lines, _) = super::lines_from_reader(t.comment_marker, t.input.as_bytes())?;
-            super::write_lines_to_writer(lines, "\n", &mut buf)?;
+            super::write_lines_to_writer(lines.into_iter().map(Ok), "\n", &mut buf)?;
             assert_eq!(unsafe { String::from_utf8_unchecked(buf) }, t.expect);
         }
 
@@ -642,8 +1501,9 @@ baz
             ),
             (
                 "Lorem ipsum dolor sit amet\tconsectetur adipiscing elit",
-                Err(super::could_not_determine_line_ending()),
+                Ok(super::DEFAULT_LINE_ENDING),
             ),
+            ("", Ok(super::DEFAULT_LINE_ENDING)),
             (
                 long_str.as_str(),
                 Err(super::could_not_determine_line_ending()),
@@ -675,6 +1535,118 @@ baz
         Ok(())
     }
 
+    #[test]
+    fn prune_missing_paths() -> Result<()> {
+        let td = tempdir()?;
+        create_dir_all(td.path().join("src"))?;
+        write(td.path().join("src/main.rs"), "")?;
+        let mut filename = td.path().to_path_buf();
+        filename.push("paths.txt");
+        write(&filename, "/src/\nsrc/lib.rs\nsrc/*.rs\n*.md\npaths.txt\n")?;
+
+        let args = |extra: &[&str]| {
+            let mut args = vec![
+                String::from("omegasort"),
+                String::from("--sort"),
+                String::from("path"),
+            ];
+            args.extend(extra.iter().map(|a| String::from(*a)));
+            args.push(filename.to_string_lossy().to_string());
+            args
+        };
+
+        let cli = Cli::new_from_args(args(&["--check", "--check-exists"]))?;
+        let res = cli.execute();
+        assert!(
+            matches!(
+                res.as_ref().map_err(|e| e.downcast_ref::<CheckError>()),
+                Err(Some(CheckError::HasMissingPaths { lines })) if lines.len() == 2,
+            ),
+            "--check-exists reports both missing paths: {res:?}",
+        );
+
+        let cli = Cli::new_from_args(args(&["--prune-missing", "--in-place"]))?;
+        cli.execute()?;
+        assert_eq!(read_to_string(&filename)?, "/src/\npaths.txt\nsrc/*.rs\n");
+
+        let cli = Cli::new_from_args(args(&["--check", "--check-exists"]))?;
+        cli.execute()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn group_continuation_lines() -> Result<()> {
+        let input = "  orphan\nb first\n  b second\n# about a\na first\n# inside\n  a second\n";
+        let (lines, _) = super::lines_from_reader(Some("#"), input.as_bytes())?;
+        let grouped = super::group_continuation_lines(lines, |l| l.starts_with(' '));
+        assert_eq!(
+            grouped
+                .iter()
+                .map(|l| (l.line.as_str(), l.continuation.clone()))
+                .collect::<Vec<_>>(),
+            [
+                ("  orphan", vec![]),
+                ("b first", vec!["  b second".to_string()]),
+                (
+                    "a first",
+                    vec!["# inside".to_string(), "  a second".to_string()],
+                ),
+            ],
+        );
+
+        let td = tempdir()?;
+        let mut filename = td.path().to_path_buf();
+        filename.push("log.txt");
+        write(&filename, "b 2\n+ b 3\na 1\n+ a 2\n+ a 3\n")?;
+        let cli = Cli::new_from_args(vec![
+            String::from("omegasort"),
+            String::from("--sort"),
+            String::from("text"),
+            String::from("--continuation"),
+            String::from("matching"),
+            String::from("--continuation-regex"),
+            String::from(r"^\+ "),
+            String::from("--in-place"),
+            filename.to_string_lossy().to_string(),
+        ])?;
+        cli.execute()?;
+        assert_eq!(
+            read_to_string(&filename)?,
+            "a 1\n+ a 2\n+ a 3\nb 2\n+ b 3\n"
+        );
+
+        // Records with the same first line aren't necessarily duplicates, so
+        // this can't be combined with --unique.
+        let res = Cli::command().try_get_matches_from([
+            "omegasort",
+            "--sort",
+            "text",
+            "--continuation",
+            "indented",
+            "--unique",
+            "--in-place",
+            "log.txt",
+        ]);
+        assert!(res.is_err(), "--continuation conflicts with --unique");
+
+        let cli = Cli::new_from_args([
+            "omegasort",
+            "--sort",
+            "path",
+            "--continuation",
+            "unparsed",
+            "--stdout",
+            "log.txt",
+        ])?;
+        assert!(
+            cli.validate_args().is_err(),
+            "--continuation unparsed is rejected when every line can be parsed",
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn bak_file_by_default() -> Result<()> {
         let td = tempdir()?;
@@ -726,6 +1698,131 @@ baz
         Ok(())
     }
 
+    #[test]
+    fn add_and_remove() -> Result<()> {
+        let td = tempdir()?;
+        let mut filename = td.path().to_path_buf();
+        filename.push("input.txt");
+        write(&filename, "bar\n# foo comment\nfoo\nquux\n")?;
+
+        let cli = Cli::new_from_args([
+            "omegasort",
+            "--sort",
+            "text",
+            "--comment-prefix",
+            "#",
+            "--in-place",
+            "add",
+            "--comment",
+            "baz comment",
+            filename.to_string_lossy().as_ref(),
+            "baz",
+        ])?;
+        cli.execute()?;
+        assert_eq!(
+            read_to_string(&filename)?,
+            "bar\n# baz comment\nbaz\n# foo comment\nfoo\nquux\n",
+        );
+
+        let cli = Cli::new_from_args([
+            "omegasort",
+            "--sort",
+            "text",
+            "--comment-prefix",
+            "#",
+            "--in-place",
+            "remove",
+            filename.to_string_lossy().as_ref(),
+            "foo",
+            "quux",
+        ])?;
+        cli.execute()?;
+        assert_eq!(read_to_string(&filename)?, "bar\n# baz comment\nbaz\n");
+
+        // An empty file has no line ending to copy.
+        write(&filename, "")?;
+        let cli = Cli::new_from_args([
+            "omegasort",
+            "--sort",
+            "text",
+            "--in-place",
+            "add",
+            filename.to_string_lossy().as_ref(),
+            "foo",
+        ])?;
+        cli.execute()?;
+        assert_eq!(
+            read_to_string(&filename)?,
+            format!("foo{}", super::DEFAULT_LINE_ENDING),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn args_checked_after_auto_detection() -> Result<()> {
+        let td = tempdir()?;
+        let mut filename = td.path().to_path_buf();
+        filename.push("ips.txt");
+        write(&filename, "10.0.0.2\n10.0.0.1\n")?;
+
+        let cli = Cli::new_from_args([
+            "omegasort",
+            "--sort",
+            "auto",
+            "--path-order",
+            "tree",
+            "--stdout",
+            filename.to_string_lossy().as_ref(),
+        ])?;
+        let res = cli.execute();
+        assert!(res.is_err(), "--path-order is rejected for an ip file");
+        assert!(
+            res.unwrap_err().downcast_ref::<ArgsError>().is_some(),
+            "the error is an ArgsError",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_requires_sorted_files() -> Result<()> {
+        let td = tempdir()?;
+        let mut sorted = td.path().to_path_buf();
+        sorted.push("sorted.txt");
+        write(&sorted, "bar\nbaz\nfoo\n")?;
+        let mut unsorted = td.path().to_path_buf();
+        unsorted.push("unsorted.txt");
+        write(&unsorted, "foo\nbar\n")?;
+
+        let cli = Cli::new_from_args([
+            String::from("omegasort"),
+            String::from("--sort"),
+            String::from("text"),
+            String::from("--merge"),
+            String::from("--check"),
+            sorted.to_string_lossy().to_string(),
+            unsorted.to_string_lossy().to_string(),
+        ])?;
+
+        let res = cli.execute();
+        assert!(res.is_err(), "cannot merge an unsorted file");
+        let e = res.unwrap_err();
+        assert!(
+            matches!(
+                e.downcast_ref::<CheckError>(),
+                Some(CheckError::NotSorted { .. }),
+            ),
+            "got a NotSorted error: {e}",
+        );
+        assert!(
+            e.to_string().contains("unsorted.txt"),
+            "error names the unsorted file: {e}",
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn integration() -> Result<()> {
         let mut test_case_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -803,6 +1900,14 @@ baz
                 matches!(check_error, CheckError::NotUnique { .. }),
                 "check_error ({check_error:?}) is a NotUnique error from --check"
             ),
+            "NotCollapsed" => assert!(
+                matches!(check_error, CheckError::NotCollapsed { .. }),
+                "check_error ({check_error:?}) is a NotCollapsed error from --check"
+            ),
+            "NotCanonical" => assert!(
+                matches!(check_error, CheckError::NotCanonical { .. }),
+                "check_error ({check_error:?}) is a NotCanonical error from --check"
+            ),
             _ => unreachable!(
                 "unexpected expected_check_failure value in test file: {expected_check_failure}"
             ),
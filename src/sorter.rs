@@ -1,89 +1,357 @@
 use crate::{
     collation::collator_for_locale,
     comparer::{
-        Comparer, DatetimeTextComparer, IpComparer, NetworkComparer, NumberedTextComparer,
-        PathComparer, PathType, TextComparer,
+        compare_two_ip_addresses, map_ipv4_address, Comparer, DateOrder, DatetimeTextComparer,
+        DomainComparer, EmailComparer, EndpointComparer, InvalidLinesComparer, IpComparer,
+        IpTextComparer, Network, NetworkComparer, NumberedTextComparer, PathComparer, PathOptions,
+        PathOrder, PathType, TextComparer, Timezone, UrlComparer,
     },
-    error::CheckError,
+    error::{CheckError, InvalidLine, Overlap, Relationship, SortError},
     SortableLine,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::ValueEnum;
+use log::debug;
 use rayon::prelude::*;
-use std::{cmp::Ordering, collections::HashMap, sync::Mutex};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::Mutex,
+};
+
+// The maximum number of lines to look at when auto-detecting the strategy.
+const AUTO_DETECT_SAMPLE_SIZE: usize = 1000;
+
+// The percentage of lines that must parse in order to pick a strategy which
+// falls back to comparing lines as text when they cannot be parsed.
+const AUTO_DETECT_THRESHOLD_PERCENT: usize = 90;
 
-#[derive(Clone, Copy, Debug, ValueEnum)]
+// The indentation for each level of a tree of paths.
+const TREE_INDENT: &str = "  ";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
 pub(crate) enum Strategy {
+    Auto,
     Text,
     NumberedText,
     DatetimeText,
     Path,
     Ip,
+    IpText,
+    Endpoint,
     Network,
+    Domain,
+    Email,
+    Url,
 }
 
 impl Strategy {
     pub(crate) fn supports_locale(self) -> bool {
-        !matches!(self, Strategy::Ip | Strategy::Network)
+        !matches!(
+            self,
+            Strategy::Ip
+                | Strategy::Endpoint
+                | Strategy::Network
+                | Strategy::Domain
+                | Strategy::Email
+                | Strategy::Url
+        )
     }
 
     pub(crate) fn supports_path_type(self) -> bool {
         matches!(self, Strategy::Path)
     }
+
+    pub(crate) fn supports_path_order(self) -> bool {
+        matches!(self, Strategy::Path)
+    }
+
+    pub(crate) fn supports_datetime_format(self) -> bool {
+        matches!(self, Strategy::DatetimeText)
+    }
+
+    // Returns true if the comparer for this strategy cannot handle lines that
+    // it cannot parse.
+    pub(crate) fn requires_valid_lines(self) -> bool {
+        matches!(
+            self,
+            Strategy::Ip
+                | Strategy::Endpoint
+                | Strategy::Network
+                | Strategy::Domain
+                | Strategy::Email
+                | Strategy::Url
+        )
+    }
+
+    // Text and path sorting accept every line, so no line is ever treated
+    // as a continuation with `--continuation unparsed`.
+    pub(crate) fn supports_unparsed_continuation(self) -> bool {
+        !matches!(self, Strategy::Text | Strategy::Path)
+    }
+
+    pub(crate) fn supports_canonicalize(self) -> bool {
+        matches!(
+            self,
+            Strategy::Path
+                | Strategy::Ip
+                | Strategy::Endpoint
+                | Strategy::Network
+                | Strategy::Domain
+                | Strategy::Email
+                | Strategy::Url
+        )
+    }
+
+    pub(crate) fn supports_ipv4_mapping(self) -> bool {
+        matches!(self, Strategy::Ip | Strategy::Endpoint)
+    }
+
+    pub(crate) fn supports_collapse(self) -> bool {
+        matches!(self, Strategy::Network)
+    }
+
+    pub(crate) fn supports_overlap_check(self) -> bool {
+        matches!(self, Strategy::Network)
+    }
+
+    pub(crate) fn supports_network_lookup(self) -> bool {
+        matches!(self, Strategy::Ip | Strategy::Endpoint | Strategy::Network)
+    }
+
+    // If `sample` is true then this only looks at up to about
+    // `AUTO_DETECT_SAMPLE_SIZE` lines, spread evenly through the file.
+    pub(crate) fn detect(lines: &[&str], sample: bool) -> Strategy {
+        let step = if sample {
+            (lines.len() / AUTO_DETECT_SAMPLE_SIZE).max(1)
+        } else {
+            1
+        };
+        let lines = lines.iter().step_by(step).copied().collect::<Vec<_>>();
+        if lines.is_empty() {
+            return Strategy::Text;
+        }
+
+        // The ip, network, endpoint, url, email, and domain comparers return
+        // an error for lines they cannot parse, so every line has to parse
+        // for these to be picked.
+        if lines
+            .iter()
+            .all(|l| IpComparer::parse_ip_address(l).is_ok())
+        {
+            return Strategy::Ip;
+        }
+        if lines
+            .iter()
+            .all(|l| NetworkComparer::parse_network(l).is_ok())
+        {
+            return Strategy::Network;
+        }
+        if lines
+            .iter()
+            .all(|l| EndpointComparer::parse_endpoint(l).is_ok())
+        {
+            return Strategy::Endpoint;
+        }
+        // URLs and email addresses can contain a `/`, so these have to come
+        // before paths.
+        if lines.iter().all(|l| UrlComparer::looks_like_url(l)) {
+            return Strategy::Url;
+        }
+        if lines.iter().all(|l| EmailComparer::parse_email(l).is_ok()) {
+            return Strategy::Email;
+        }
+        if lines.iter().all(|l| DomainComparer::looks_like_domain(l)) {
+            return Strategy::Domain;
+        }
+
+        let mostly = |f: fn(&str) -> bool| {
+            let count = lines.iter().filter(|l| f(l)).count();
+            count * 100 >= lines.len() * AUTO_DETECT_THRESHOLD_PERCENT
+        };
+        // This has to come before numbered text, since a line starting with
+        // an IPv4 address also starts with a number.
+        if mostly(|l| IpTextComparer::ip_and_rest(l).is_some()) {
+            return Strategy::IpText;
+        }
+        if mostly(|l| DatetimeTextComparer::datetime_from_str(l).is_some()) {
+            return Strategy::DatetimeText;
+        }
+        if mostly(NumberedTextComparer::starts_with_number) {
+            return Strategy::NumberedText;
+        }
+        if mostly(PathComparer::looks_like_path) {
+            return Strategy::Path;
+        }
+
+        Strategy::Text
+    }
+}
+
+// This determines what happens to lines that a strategy cannot parse. This
+// only applies to strategies that require every line to be valid.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub(crate) enum InvalidLinePolicy {
+    /// Sort invalid lines as text before all of the valid lines.
+    First,
+    /// Sort invalid lines as text after all of the valid lines.
+    Last,
+    /// Stop with an error that lists every invalid line.
+    #[default]
+    Error,
+    /// Remove invalid lines from the output.
+    Drop,
+}
+
+#[derive(Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct SorterOptions<'a> {
+    pub(crate) locale: Option<&'a str>,
+    pub(crate) unique: bool,
+    pub(crate) case_insensitive: bool,
+    pub(crate) reverse: bool,
+    pub(crate) path_type: PathType,
+    pub(crate) path_order: PathOrder,
+    pub(crate) dirs_first: bool,
+    pub(crate) natural: bool,
+    pub(crate) normalize: bool,
+    pub(crate) indent: bool,
+    pub(crate) datetime_formats: &'a [String],
+    pub(crate) timezone: Timezone,
+    pub(crate) date_order: DateOrder,
+    pub(crate) invalid: InvalidLinePolicy,
+    pub(crate) canonicalize: bool,
+    pub(crate) collapse: bool,
+    pub(crate) aggregate: bool,
+    pub(crate) no_overlap: bool,
+    pub(crate) map_ipv4: bool,
 }
 
+#[allow(clippy::struct_excessive_bools)]
 pub(crate) struct Sorter {
+    strategy: Strategy,
     comparer: Box<dyn Comparer + Sync>,
+    // This is only set when paths should be indented as a tree in the
+    // output.
+    indent: Option<PathComparer>,
     unique: bool,
     reverse: bool,
+    invalid: InvalidLinePolicy,
+    canonicalize: bool,
+    collapse: bool,
+    aggregate: bool,
+    no_overlap: bool,
+    map_ipv4: bool,
 }
 
 impl Sorter {
-    #[allow(clippy::fn_params_excessive_bools)]
-    pub(crate) fn new(
-        strategy: Strategy,
-        locale_name: Option<&str>,
-        unique: bool,
-        case_insensitive: bool,
-        reverse: bool,
-        windows: bool,
-    ) -> Result<Sorter> {
-        let collator = if let Some(locale_name) = locale_name {
+    pub(crate) fn new(strategy: Strategy, opts: &SorterOptions) -> Result<Sorter> {
+        // Windows paths are case-insensitive, so normalizing them also means
+        // comparing them case-insensitively. With mixed paths, any line may be
+        // a Windows path, so every line is compared this way.
+        let case_insensitive = opts.case_insensitive
+            || (strategy == Strategy::Path && opts.path_type != PathType::Unix && opts.normalize);
+        let collator = if let Some(locale_name) = opts.locale {
             Some(collator_for_locale(locale_name, case_insensitive)?)
         } else {
             None
         };
-        let comparer: Box<dyn Comparer + Sync> = match strategy {
+        let path_opts = PathOptions {
+            path_type: opts.path_type,
+            order: opts.path_order,
+            dirs_first: opts.dirs_first,
+            natural: opts.natural,
+            normalize: opts.normalize,
+        };
+        let mut comparer: Box<dyn Comparer + Sync> = match strategy {
+            Strategy::Auto => unreachable!("the auto strategy is resolved before sorting"),
             Strategy::Text => Box::new(TextComparer::new(collator, case_insensitive)),
             Strategy::NumberedText => {
                 Box::new(NumberedTextComparer::new(collator, case_insensitive))
             }
-            Strategy::DatetimeText => {
-                Box::new(DatetimeTextComparer::new(collator, case_insensitive))
-            }
-            Strategy::Path => Box::new(PathComparer::new(
+            Strategy::DatetimeText => Box::new(DatetimeTextComparer::new(
                 collator,
                 case_insensitive,
-                if windows {
-                    PathType::Windows
-                } else {
-                    PathType::Unix
-                },
-            )),
-            Strategy::Ip => Box::new(IpComparer::new()),
+                opts.datetime_formats,
+                opts.timezone,
+                opts.date_order,
+            )?),
+            Strategy::Path => Box::new(PathComparer::new(collator, case_insensitive, path_opts)),
+            Strategy::Ip => Box::new(IpComparer::new(opts.map_ipv4)),
+            Strategy::Endpoint => Box::new(EndpointComparer::new(opts.map_ipv4)),
+            Strategy::IpText => Box::new(IpTextComparer::new(collator, case_insensitive)),
             Strategy::Network => Box::new(NetworkComparer::new()),
+            Strategy::Domain => Box::new(DomainComparer::new()),
+            Strategy::Email => Box::new(EmailComparer::new()),
+            Strategy::Url => Box::new(UrlComparer::new()),
         };
+        if matches!(
+            opts.invalid,
+            InvalidLinePolicy::First | InvalidLinePolicy::Last
+        ) {
+            // The sorted lines are reversed after sorting, so we need to flip
+            // this to make sure the invalid lines end up where they belong.
+            let invalid_first = (opts.invalid == InvalidLinePolicy::First) != opts.reverse;
+            comparer = Box::new(InvalidLinesComparer::new(comparer, invalid_first));
+        }
+        // Deciding whether one path contains another only needs to know
+        // whether two components are the same, so this doesn't need the
+        // collator.
+        let indent = (opts.indent && strategy == Strategy::Path)
+            .then(|| PathComparer::new(None, case_insensitive, path_opts));
         Ok(Self {
+            strategy,
             comparer,
-            unique,
-            reverse,
+            indent,
+            unique: opts.unique,
+            reverse: opts.reverse,
+            invalid: opts.invalid,
+            canonicalize: opts.canonicalize,
+            // Aggregating networks only makes sense once any networks that
+            // are contained in other networks are removed.
+            collapse: opts.collapse || opts.aggregate,
+            aggregate: opts.aggregate,
+            no_overlap: opts.no_overlap,
+            map_ipv4: opts.map_ipv4,
         })
     }
 
     pub(crate) fn lines_are_sorted(&self, lines: &[SortableLine]) -> Result<bool> {
+        let invalid_lines = self.invalid_lines(lines);
+        if !invalid_lines.is_empty() {
+            // If we're dropping invalid lines then having them in the file
+            // means it is not sorted, since sorting would remove them.
+            return Err(if self.invalid == InvalidLinePolicy::Drop {
+                CheckError::HasInvalidLines {
+                    lines: invalid_lines,
+                }
+                .into()
+            } else {
+                SortError::InvalidLines {
+                    lines: invalid_lines,
+                }
+                .into()
+            });
+        }
+
+        if self.canonicalize {
+            for line in lines {
+                let canonical = self.comparer.canonicalize(&line.line)?;
+                if canonical != line.line {
+                    return Err(CheckError::NotCanonical {
+                        line_number: line.line_number,
+                        line: line.line.clone(),
+                        canonical,
+                    }
+                    .into());
+                }
+            }
+        }
+
         let mut last_line: Option<&str> = None;
 
-        let mut seen_lines: Option<HashMap<&str, usize>> = None;
+        let mut seen_lines: Option<HashMap<String, usize>> = None;
         if self.unique {
             seen_lines = Some(HashMap::new());
         }
@@ -101,7 +369,8 @@ impl Sorter {
 
             if self.unique {
                 let seen_lines = seen_lines.as_mut().unwrap();
-                if let Some(seen) = seen_lines.get(line.line.as_str()) {
+                let key = self.comparer.unique_key(&line.line)?;
+                if let Some(seen) = seen_lines.get(&key) {
                     return Err(CheckError::NotUnique {
                         line1: *seen,
                         line2: line.line_number,
@@ -109,20 +378,164 @@ impl Sorter {
                     }
                     .into());
                 }
-                seen_lines.insert(&line.line, line.line_number);
+                seen_lines.insert(key, line.line_number);
             }
 
             last_line = Some(&line.line);
         }
 
+        if self.collapse {
+            let mut lines = lines.to_vec();
+            if self.reverse {
+                lines.reverse();
+            }
+            let (_, collapsed) = self.collapse_networks(lines)?;
+            if let Some(c) = collapsed.into_iter().next() {
+                return Err(CheckError::NotCollapsed {
+                    line_number: c.line_number,
+                    line: c.line,
+                    network: c.network,
+                }
+                .into());
+            }
+        }
+
+        if self.no_overlap {
+            let overlaps = self.overlapping_networks(lines)?;
+            if !overlaps.is_empty() {
+                return Err(CheckError::HasOverlappingNetworks { overlaps }.into());
+            }
+        }
+
         Ok(true)
     }
 
+    // The lines must already be sorted. Returns every network which overlaps
+    // a network that comes before it in the file. Any lines which cannot be
+    // parsed are ignored.
+    fn overlapping_networks(&self, lines: &[SortableLine]) -> Result<Vec<Overlap>> {
+        // We always walk the lines in ascending order, which puts larger
+        // networks before the networks they contain.
+        let ascending: Box<dyn Iterator<Item = &SortableLine>> = if self.reverse {
+            Box::new(lines.iter().rev())
+        } else {
+            Box::new(lines.iter())
+        };
+
+        let mut overlaps = vec![];
+        let mut container: Option<(&SortableLine, Network)> = None;
+        let mut previous: Option<(&SortableLine, Network)> = None;
+        for line in ascending {
+            if self.comparer.check_line(&line.line).is_err() {
+                continue;
+            }
+
+            let net = NetworkComparer::parse_network(&line.line)?;
+            // Since the lines are sorted by their starting address, with
+            // larger networks first, a duplicate is always next to the network
+            // it duplicates, and any other overlapping network must overlap
+            // the last network which was not itself contained in another
+            // network.
+            let overlapped = match (previous, container) {
+                (Some((p, p_net)), _) if p_net.same_addresses(&net) => {
+                    Some((p, Relationship::Duplicate))
+                }
+                (_, Some((c, c_net))) => self.relationship(&c_net, &net).map(|r| (c, r)),
+                _ => None,
+            };
+            previous = Some((line, net));
+            if !container.is_some_and(|(_, c_net)| c_net.contains(&net)) {
+                container = Some((line, net));
+            }
+
+            let Some((other, relationship)) = overlapped else {
+                continue;
+            };
+            let (first, second) = if self.reverse {
+                (line, other)
+            } else {
+                (other, line)
+            };
+            overlaps.push(Overlap {
+                first_line_number: first.line_number,
+                first: first.line.clone(),
+                second_line_number: second.line_number,
+                second: second.line.clone(),
+                relationship,
+            });
+        }
+
+        overlaps.sort_by_key(|o| (o.second_line_number, o.first_line_number));
+        Ok(overlaps)
+    }
+
+    // Returns every line that does not pass the comparer's validation, which
+    // is stricter than what the comparer needs in order to sort the lines.
+    pub(crate) fn validate_lines(&self, lines: &[SortableLine]) -> Vec<InvalidLine> {
+        find_invalid_lines(lines, |l| self.comparer.validate_line(l))
+    }
+
+    pub(crate) fn is_valid_line(&self, line: &str) -> bool {
+        self.comparer.validate_line(line).is_ok()
+    }
+
+    // When the comparer is wrapped in an `InvalidLinesComparer` this will
+    // always be empty, since that comparer can handle any line.
+    fn invalid_lines(&self, lines: &[SortableLine]) -> Vec<InvalidLine> {
+        find_invalid_lines(lines, |l| self.comparer.check_line(l))
+    }
+
     fn is_ordered(&self, str1: &str, str2: &str) -> Result<bool> {
         self.comparer.is_ordered(str1, str2, self.reverse)
     }
 
     pub(crate) fn sort_lines(&self, mut lines: Vec<SortableLine>) -> Result<Vec<SortableLine>> {
+        let invalid_lines = self.invalid_lines(&lines);
+        if !invalid_lines.is_empty() {
+            if self.invalid != InvalidLinePolicy::Drop {
+                return Err(SortError::InvalidLines {
+                    lines: invalid_lines,
+                }
+                .into());
+            }
+            for l in &invalid_lines {
+                debug!("dropping invalid line - {l}");
+            }
+            lines.retain(|l| self.comparer.check_line(&l.line).is_ok());
+        }
+
+        // We canonicalize before sorting so that lines which are only
+        // different before canonicalization end up next to each other and can
+        // be deduplicated.
+        if self.canonicalize {
+            for line in &mut lines {
+                line.line = self.comparer.canonicalize(&line.line)?;
+            }
+        }
+
+        self.sort_by_comparer(&mut lines)?;
+
+        if self.collapse {
+            let (collapsed, _) = self.collapse_networks(lines)?;
+            lines = collapsed;
+        }
+
+        if self.reverse {
+            lines.reverse();
+        }
+
+        if self.unique {
+            lines = self.dedup_lines(lines)?;
+        }
+
+        if let Some(paths) = &self.indent {
+            Self::indent_tree(paths, &mut lines);
+        }
+
+        Ok(lines)
+    }
+
+    fn sort_by_comparer(&self, lines: &mut [SortableLine]) -> Result<()> {
         let res = Mutex::new(Ok(()));
         lines.par_sort_by(|a, b| match self.comparer.cmp(&a.line, &b.line) {
             Ok(o) => o,
@@ -135,36 +548,488 @@ impl Sorter {
         });
         // The first `?` is for the `MutexGuard` and the second is for the
         // underlying `Result`.
-        res.into_inner()??;
+        res.into_inner()?
+    }
 
-        if self.reverse {
-            lines.reverse();
+    // Lines with the same unique key are always next to each other once they
+    // are sorted, so this only needs to look at the previous line.
+    fn dedup_lines(&self, lines: Vec<SortableLine>) -> Result<Vec<SortableLine>> {
+        let mut deduped = Vec::with_capacity(lines.len());
+        let mut last_key = None;
+        for line in lines {
+            let key = self.comparer.unique_key(&line.line)?;
+            if last_key.as_ref() == Some(&key) {
+                continue;
+            }
+            last_key = Some(key);
+            deduped.push(line);
         }
+        Ok(deduped)
+    }
 
-        if self.unique {
-            lines.dedup_by(|a, b| a.line == b.line);
+    // This indents each path, along with its comment, by two spaces for each
+    // earlier path that contains it. This expects the lines to be sorted in
+    // tree order.
+    fn indent_tree(paths: &PathComparer, lines: &mut [SortableLine]) {
+        let mut ancestors: Vec<String> = vec![];
+        for line in lines {
+            while let Some(last) = ancestors.last() {
+                if paths.is_ancestor(last, &line.line) {
+                    break;
+                }
+                ancestors.pop();
+            }
+            let indent = TREE_INDENT.repeat(ancestors.len());
+            ancestors.push(line.line.clone());
+            if indent.is_empty() {
+                continue;
+            }
+
+            line.line = format!("{indent}{}", line.line);
+            if let Some(comment) = &mut line.comment {
+                for l in &mut comment.lines {
+                    *l = format!("{indent}{l}");
+                }
+            }
         }
+    }
 
-        Ok(lines)
+    // Returns how the network which comes later in ascending order relates to
+    // the one which comes earlier. When the lines are reversed, the later
+    // network comes first in the file, so a subset becomes a superset.
+    fn relationship(&self, earlier: &Network, later: &Network) -> Option<Relationship> {
+        let (subset, superset) = if self.reverse {
+            (Relationship::Superset, Relationship::Subset)
+        } else {
+            (Relationship::Subset, Relationship::Superset)
+        };
+        if earlier.same_addresses(later) {
+            Some(Relationship::Duplicate)
+        } else if earlier.contains(later) {
+            Some(subset)
+        } else if later.contains(earlier) {
+            Some(superset)
+        } else if earlier.overlaps(later) {
+            Some(Relationship::Overlap)
+        } else {
+            None
+        }
+    }
+
+    // This removes every network that is contained in another network, and if the `aggregate`
+    // flag is set it also replaces pairs of adjacent networks with the
+    // network that covers both of them, repeating this until no more pairs
+    // can be merged. The comments for removed networks are added to the
+    // network that replaces them. Any lines which cannot be parsed are left
+    // as-is.
+    //
+    // This returns the remaining lines in ascending order along with a record
+    // of each network that was removed.
+    fn collapse_networks(
+        &self,
+        lines: Vec<SortableLine>,
+    ) -> Result<(Vec<SortableLine>, Vec<CollapsedNetwork>)> {
+        let mut invalid = vec![];
+        let mut networks = vec![];
+        for line in lines {
+            if self.comparer.check_line(&line.line).is_err() {
+                invalid.push(line);
+            } else {
+                let net = NetworkComparer::parse_network(&line.line)?;
+                networks.push((line, net));
+            }
+        }
+        // The comparer sorts networks by their address as written, which may
+        // have host bits set, so `10.0.0.5/8` sorts after `10.0.0.0/24`. To
+        // collapse them we need them sorted by the first address in each
+        // network instead, with larger networks first. This sort is stable,
+        // so of two networks with the same addresses the earlier one is kept.
+        networks.sort_by(|(_, a), (_, b)| {
+            a.first()
+                .cmp(&b.first())
+                .then_with(|| b.last().cmp(&a.last()))
+        });
+
+        let mut kept: Vec<(SortableLine, Network)> = vec![];
+        let mut collapsed = vec![];
+        for (line, net) in networks {
+            // Since the networks are sorted by their first address, with
+            // larger networks first, a network can only be contained in the
+            // last network we kept.
+            if let Some((last, last_net)) = kept.last_mut() {
+                if last_net.contains(&net) {
+                    debug!("`{}` is contained in `{}`", line.line, last.line);
+                    collapsed.push(CollapsedNetwork::new(&line, last.line.clone()));
+                    last.append_comment(line.comment);
+                    continue;
+                }
+            }
+            kept.push((line, net));
+
+            if !self.aggregate {
+                continue;
+            }
+            // Only networks which can be written in CIDR notation can be
+            // aggregated.
+            while kept.len() >= 2 {
+                let (Some(net1), Some(net2)) = (
+                    kept[kept.len() - 2].1.as_cidr(),
+                    kept[kept.len() - 1].1.as_cidr(),
+                ) else {
+                    break;
+                };
+                if !net1.is_sibling(&net2) {
+                    break;
+                }
+                // This unwrap is safe because a network with a sibling always
+                // has a supernet.
+                let supernet = net1.supernet().unwrap();
+                debug!("aggregating `{net1}` and `{net2}` into `{supernet}`");
+
+                let (upper, _) = kept.pop().unwrap();
+                let (mut lower, _) = kept.pop().unwrap();
+                for l in [&lower, &upper] {
+                    collapsed.push(CollapsedNetwork::new(l, supernet.to_string()));
+                }
+                lower.line = supernet.to_string();
+                lower.append_comment(upper.comment);
+                kept.push((lower, Network::Cidr(supernet)));
+            }
+        }
+
+        let mut lines = kept.into_iter().map(|(l, _)| l).collect::<Vec<_>>();
+        lines.extend(invalid);
+        self.sort_by_comparer(&mut lines)?;
+        Ok((lines, collapsed))
+    }
+
+    // The lines must already be sorted. Returns false if the `unique` flag is
+    // set and the line is already present, in which case the lines are left
+    // untouched.
+    pub(crate) fn add_line(
+        &self,
+        lines: &mut Vec<SortableLine>,
+        mut line: SortableLine,
+    ) -> Result<bool> {
+        line.line = self.canonical_line(&line.line)?;
+        if self.unique && !self.duplicate_indexes(lines, &line.line)?.is_empty() {
+            return Ok(false);
+        }
+
+        let index = self.upper_bound(lines, &line.line)?;
+        debug!("inserting `{}` at index {index}", line.line);
+        lines.insert(index, line);
+        Ok(true)
+    }
+
+    // The lines must already be sorted. Returns the number of lines which
+    // were removed.
+    pub(crate) fn remove_line(&self, lines: &mut Vec<SortableLine>, line: &str) -> Result<usize> {
+        let line = self.canonical_line(line)?;
+        let indexes = self.duplicate_indexes(lines, &line)?;
+        for i in indexes.iter().rev() {
+            lines.remove(*i);
+        }
+        Ok(indexes.len())
+    }
+
+    // The lines must already be sorted. Returns the index of every line with
+    // the same unique key as the given line. Lines with the same key are
+    // not always equal according to the comparer, so this also looks at the
+    // lines on either side of the lines which compare as equal.
+    fn duplicate_indexes(&self, lines: &[SortableLine], line: &str) -> Result<Vec<usize>> {
+        let key = self.comparer.unique_key(line)?;
+        let mut start = self.lower_bound(lines, line)?;
+        let mut end = self.upper_bound(lines, line)?;
+        while start > 0 && self.comparer.unique_key(&lines[start - 1].line)? == key {
+            start -= 1;
+        }
+        while end < lines.len() && self.comparer.unique_key(&lines[end].line)? == key {
+            end += 1;
+        }
+
+        let mut indexes = vec![];
+        for (i, l) in lines.iter().enumerate().take(end).skip(start) {
+            if self.comparer.unique_key(&l.line)? == key {
+                indexes.push(i);
+            }
+        }
+        Ok(indexes)
+    }
+
+    // Returns the line unchanged unless the `canonicalize` flag is set.
+    fn canonical_line(&self, line: &str) -> Result<String> {
+        if self.canonicalize {
+            self.comparer.canonicalize(line)
+        } else {
+            Ok(line.to_string())
+        }
+    }
+
+    // The lines must already be sorted. Returns every line that compares as
+    // equal to the given value. With `--canonicalize` the lines are stored in
+    // their canonical form, so the value is canonicalized too.
+    pub(crate) fn find_equal<'a>(
+        &self,
+        lines: &'a [SortableLine],
+        value: &str,
+    ) -> Result<&'a [SortableLine]> {
+        let value = self.canonical_line(value)?;
+        let start = self.lower_bound(lines, &value)?;
+        let end = self.upper_bound(lines, &value)?;
+        Ok(&lines[start..end])
+    }
+
+    // The lines must already be sorted. Returns every line that starts with
+    // the given prefix. This only gives correct results when the lines are
+    // sorted by codepoint, possibly ignoring case, since otherwise the lines
+    // with a given prefix may not be next to each other.
+    pub(crate) fn find_with_prefix<'a>(
+        &self,
+        lines: &'a [SortableLine],
+        prefix: &str,
+        case_insensitive: bool,
+    ) -> Result<&'a [SortableLine]> {
+        let has_prefix = |line: &str| {
+            if case_insensitive {
+                line.to_lowercase().starts_with(&prefix.to_lowercase())
+            } else {
+                line.starts_with(prefix)
+            }
+        };
+
+        // Every line with the prefix sorts after the prefix itself. When the
+        // lines are in reverse order, the lines that come before the block
+        // of prefixed lines are those which sort after the prefix but don't
+        // start with it.
+        let start = partition_point(lines, |l| {
+            let ord = self.comparer.cmp(l, prefix)?;
+            Ok(if self.reverse {
+                ord.is_gt() && !has_prefix(l)
+            } else {
+                ord.is_lt()
+            })
+        })?;
+        let len = lines[start..]
+            .iter()
+            .take_while(|l| has_prefix(&l.line))
+            .count();
+        Ok(&lines[start..start + len])
+    }
+
+    // The lines must already be sorted. Returns every line that is an
+    // address or network contained in the given network.
+    pub(crate) fn find_within<'a>(
+        &self,
+        lines: &'a [SortableLine],
+        network: &str,
+    ) -> Result<Vec<&'a SortableLine>> {
+        let network = NetworkComparer::parse_network(network)?;
+        let (first, last) = (network.first(), network.last());
+
+        // If invalid lines are sorted first or last then we need to skip over
+        // them.
+        let start = partition_point(lines, |l| {
+            let addr = match self.address_for_line(l) {
+                Ok(addr) => addr,
+                Err(_) if self.invalid == InvalidLinePolicy::First => return Ok(true),
+                Err(_) if self.invalid == InvalidLinePolicy::Last => return Ok(false),
+                Err(e) => return Err(e),
+            };
+            Ok(if self.reverse {
+                compare_two_ip_addresses(addr, last).is_gt()
+            } else {
+                compare_two_ip_addresses(addr, first).is_lt()
+            })
+        })?;
+
+        let mut found = vec![];
+        for line in &lines[start..] {
+            let addr = match self.address_for_line(&line.line) {
+                Ok(addr) => addr,
+                Err(_) if self.invalid == InvalidLinePolicy::Last => break,
+                Err(e) => return Err(e),
+            };
+            let past_end = if self.reverse {
+                compare_two_ip_addresses(addr, first).is_lt()
+            } else {
+                compare_two_ip_addresses(addr, last).is_gt()
+            };
+            if past_end {
+                break;
+            }
+            let contained = match self.strategy {
+                Strategy::Network => network.contains(&NetworkComparer::parse_network(&line.line)?),
+                _ => network.contains(&Network::Host(addr)),
+            };
+            if contained {
+                found.push(line);
+            }
+        }
+
+        Ok(found)
+    }
+
+    fn address_for_line(&self, line: &str) -> Result<IpAddr> {
+        let addr = match self.strategy {
+            Strategy::Ip => IpComparer::parse_ip_address(line)?,
+            Strategy::Endpoint => EndpointComparer::parse_endpoint(line)?.addr,
+            Strategy::Network => NetworkComparer::parse_network(line)?.addr(),
+            _ => {
+                return Err(anyhow!(
+                    "cannot look up networks when sorting by {:?}",
+                    self.strategy,
+                ))
+            }
+        };
+        Ok(map_ipv4_address(addr, self.map_ipv4))
+    }
+
+    // Returns the index of the first line that does not sort before the given
+    // line.
+    fn lower_bound(&self, lines: &[SortableLine], line: &str) -> Result<usize> {
+        partition_point(lines, |l| Ok(self.cmp_in_order(l, line)?.is_lt()))
+    }
+
+    // Returns the index of the first line that sorts after the given line.
+    fn upper_bound(&self, lines: &[SortableLine], line: &str) -> Result<usize> {
+        partition_point(lines, |l| Ok(!self.cmp_in_order(l, line)?.is_gt()))
+    }
+
+    fn cmp_in_order(&self, str1: &str, str2: &str) -> Result<Ordering> {
+        let ord = self.comparer.cmp(str1, str2)?;
+        Ok(if self.reverse { ord.reverse() } else { ord })
+    }
+
+    // Each of the inputs must already be sorted. The lines are produced one
+    // at a time in sorted order, and equal lines are produced in the order of
+    // the inputs they came from, even with `--reverse`.
+    pub(crate) fn merge_lines(&self, inputs: Vec<Vec<SortableLine>>) -> MergedLines<'_> {
+        MergedLines {
+            sorter: self,
+            inputs: inputs.into_iter().map(VecDeque::from).collect(),
+            last_key: None,
+        }
+    }
+}
+
+fn find_invalid_lines<F>(lines: &[SortableLine], check: F) -> Vec<InvalidLine>
+where
+    F: Fn(&str) -> Result<()>,
+{
+    lines
+        .iter()
+        .filter_map(|l| {
+            check(&l.line).err().map(|e| InvalidLine {
+                line_number: l.line_number,
+                line: l.line.clone(),
+                error: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+// This is like `slice::partition_point` except that the predicate can return
+// an error.
+fn partition_point<P>(lines: &[SortableLine], mut pred: P) -> Result<usize>
+where
+    P: FnMut(&str) -> Result<bool>,
+{
+    let mut low = 0;
+    let mut high = lines.len();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(&lines[mid].line)? {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+// A network which was removed by `Sorter::collapse_networks`, along with the
+// network that it was collapsed into.
+struct CollapsedNetwork {
+    line_number: usize,
+    line: String,
+    network: String,
+}
+
+impl CollapsedNetwork {
+    fn new(line: &SortableLine, network: String) -> Self {
+        Self {
+            line_number: line.line_number,
+            line: line.line.clone(),
+            network,
+        }
+    }
+}
+
+pub(crate) struct MergedLines<'a> {
+    sorter: &'a Sorter,
+    inputs: Vec<VecDeque<SortableLine>>,
+    last_key: Option<String>,
+}
+
+impl Iterator for MergedLines<'_> {
+    type Item = Result<SortableLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut next: Option<usize> = None;
+            for i in 0..self.inputs.len() {
+                let Some(candidate) = self.inputs[i].front() else {
+                    continue;
+                };
+                if let Some(n) = next {
+                    // This unwrap is safe because we only set `next` to an
+                    // index for an input with a line left.
+                    let current = self.inputs[n].front().unwrap();
+                    // Equal lines are output in the order the files were
+                    // given, so a later input only wins if its line comes
+                    // strictly first.
+                    match self.sorter.is_ordered(&current.line, &candidate.line) {
+                        Ok(true) => next = Some(i),
+                        Ok(false) => (),
+                        Err(e) => return Some(Err(e)),
+                    }
+                } else {
+                    next = Some(i);
+                }
+            }
+
+            // If `next` is `None` then every input is exhausted.
+            let line = self.inputs[next?].pop_front().unwrap();
+            if self.sorter.unique {
+                let key = match self.sorter.comparer.unique_key(&line.line) {
+                    Ok(k) => k,
+                    Err(e) => return Some(Err(e)),
+                };
+                if self.last_key.as_ref() == Some(&key) {
+                    continue;
+                }
+                self.last_key = Some(key);
+            }
+
+            return Some(Ok(line));
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Sorter, Strategy};
+    use super::{InvalidLinePolicy, Sorter, SorterOptions, Strategy};
+    use crate::comparer::{PathOrder, PathType};
+    use crate::error::{CheckError, Relationship, SortError};
     use crate::SortableLine;
     use anyhow::Result;
     use test_log::test;
 
     #[test]
     fn sort_lines() -> Result<()> {
-        let sorter = Sorter::new(Strategy::Text, None, false, false, false, false)?;
-        let lines = ["foo", "bar", "quux", "baz"]
-            .into_iter()
-            .enumerate()
-            .map(|l| (l.0 + 1, l.1))
-            .map(SortableLine::from_number_and_str)
-            .collect::<Vec<_>>();
+        let sorter = Sorter::new(Strategy::Text, &SorterOptions::default())?;
+        let lines = SortableLine::numbered(&["foo", "bar", "quux", "baz"]);
         let sorted_lines = sorter.sort_lines(lines.clone())?;
 
         let mut expect = [(2, "bar"), (4, "baz"), (1, "foo"), (3, "quux")]
@@ -173,11 +1038,875 @@ mod test {
             .collect::<Vec<_>>();
         assert_eq!(sorted_lines, expect, "got expected ascending sorting");
 
-        let sorter = Sorter::new(Strategy::Text, None, false, false, true, false)?;
+        let sorter = Sorter::new(
+            Strategy::Text,
+            &SorterOptions {
+                reverse: true,
+                ..Default::default()
+            },
+        )?;
         let sorted_lines = sorter.sort_lines(lines)?;
         expect.reverse();
         assert_eq!(sorted_lines, expect, "got expected descending sorting");
 
         Ok(())
     }
+
+    #[test]
+    fn invalid_lines() -> Result<()> {
+        let lines = SortableLine::numbered(&["10.0.0.1", "host.example", "1.2.3.4", "bogus"]);
+        let sorted = |invalid, reverse| -> Result<Vec<String>> {
+            let sorter = Sorter::new(
+                Strategy::Ip,
+                &SorterOptions {
+                    reverse,
+                    invalid,
+                    ..Default::default()
+                },
+            )?;
+            Ok(sorter
+                .sort_lines(lines.clone())?
+                .into_iter()
+                .map(|l| l.line)
+                .collect())
+        };
+
+        assert_eq!(
+            sorted(InvalidLinePolicy::First, false)?,
+            ["bogus", "host.example", "1.2.3.4", "10.0.0.1"],
+        );
+        assert_eq!(
+            sorted(InvalidLinePolicy::Last, false)?,
+            ["1.2.3.4", "10.0.0.1", "bogus", "host.example"],
+        );
+        assert_eq!(
+            sorted(InvalidLinePolicy::Last, true)?,
+            ["10.0.0.1", "1.2.3.4", "host.example", "bogus"],
+            "invalid lines are still last with --reverse",
+        );
+        assert_eq!(
+            sorted(InvalidLinePolicy::Drop, false)?,
+            ["1.2.3.4", "10.0.0.1"],
+        );
+
+        let res = sorted(InvalidLinePolicy::Error, false);
+        assert!(res.is_err(), "invalid lines are an error by default");
+        let e = res.unwrap_err();
+        match e.downcast_ref::<SortError>() {
+            Some(SortError::InvalidLines { lines }) => assert_eq!(
+                lines.iter().map(|l| l.line_number).collect::<Vec<_>>(),
+                [2, 4],
+                "error includes every invalid line",
+            ),
+            _ => panic!("expected an InvalidLines error: {e}"),
+        }
+
+        let sorter = Sorter::new(
+            Strategy::Ip,
+            &SorterOptions {
+                invalid: InvalidLinePolicy::Drop,
+                ..Default::default()
+            },
+        )?;
+        let res = sorter.lines_are_sorted(&lines);
+        assert!(
+            matches!(
+                res.as_ref().map_err(|e| e.downcast_ref::<CheckError>()),
+                Err(Some(CheckError::HasInvalidLines { .. })),
+            ),
+            "lines to drop mean the lines are not sorted: {res:?}",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_lines() -> Result<()> {
+        let cases: &[(Strategy, &[&str], &[usize])] = &[
+            (Strategy::Text, &["foo", "1. bar"], &[]),
+            (Strategy::NumberedText, &["1. foo", "bar", "2.5 baz"], &[2]),
+            (
+                Strategy::IpText,
+                &["127.0.0.1 localhost", "127.0.0.1.localhost", "::1"],
+                &[2],
+            ),
+            (
+                Strategy::DatetimeText,
+                &["2017-01-12", "no datetime", "1991-01-02T01:02:03Z"],
+                &[2],
+            ),
+            (Strategy::Ip, &["1.1.1.1", "1.1.1.1/32", "::1"], &[2]),
+            (
+                Strategy::Network,
+                &[
+                    "10.0.0.0/8",
+                    "10.0.0.1/8",
+                    "10.0.0.1",
+                    "10.0.0.9-10.0.0.1",
+                    "::/0",
+                ],
+                &[2, 4],
+            ),
+        ];
+        for (strategy, lines, expect) in cases {
+            let lines = SortableLine::numbered(lines);
+            let sorter = Sorter::new(*strategy, &SorterOptions::default())?;
+            assert_eq!(
+                sorter
+                    .validate_lines(&lines)
+                    .iter()
+                    .map(|l| l.line_number)
+                    .collect::<Vec<_>>(),
+                *expect,
+                "{strategy:?}",
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_lines() -> Result<()> {
+        let cases: &[(Strategy, PathType, &[&str], &[&str])] = &[
+            (
+                Strategy::Ip,
+                PathType::Unix,
+                &["2001:DB8:0:0::1", "10.0.0.1", "2001:db8::1"],
+                &["10.0.0.1", "2001:db8::1"],
+            ),
+            (
+                Strategy::Network,
+                PathType::Unix,
+                &["10.0.0.1/8", "10.0.0.0/8", "2001:DB8::1/32"],
+                &["10.0.0.0/8", "2001:db8::/32"],
+            ),
+            (
+                Strategy::Path,
+                PathType::Unix,
+                &["./a//b/", "a/b", "/x/./y", ".", "../c"],
+                &["/x/y", ".", "../c", "a/b"],
+            ),
+            (
+                Strategy::Path,
+                PathType::Windows,
+                &["C:/foo//bar", r"C:\foo\bar", r".\baz\"],
+                &[r"C:\foo\bar", "baz"],
+            ),
+        ];
+        for (strategy, path_type, input, expect) in cases {
+            let lines = SortableLine::numbered(input);
+            let sorter = Sorter::new(
+                *strategy,
+                &SorterOptions {
+                    unique: true,
+                    path_type: *path_type,
+                    canonicalize: true,
+                    ..Default::default()
+                },
+            )?;
+            assert!(
+                matches!(
+                    sorter
+                        .lines_are_sorted(&lines)
+                        .as_ref()
+                        .map_err(|e| e.downcast_ref::<CheckError>()),
+                    Err(Some(CheckError::NotCanonical { .. })),
+                ),
+                "{strategy:?} lines are not canonical",
+            );
+            let sorted = sorter.sort_lines(lines)?;
+            assert_eq!(
+                sorted.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(),
+                *expect,
+                "{strategy:?}",
+            );
+            assert!(
+                sorter.lines_are_sorted(&sorted)?,
+                "{strategy:?} canonical lines are sorted",
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unique_without_canonicalize() -> Result<()> {
+        let cases: &[(Strategy, &[&str], &[&str])] = &[
+            (
+                Strategy::Domain,
+                &["Example.COM", "example.com.", "example.org"],
+                &["Example.COM", "example.org"],
+            ),
+            (
+                Strategy::Email,
+                &["user@Example.COM", "user@example.com", "User@example.com"],
+                &["User@example.com", "user@Example.COM"],
+            ),
+            (
+                Strategy::Url,
+                &[
+                    "HTTPS://X.com/a",
+                    "https://x.com:443/a/",
+                    "https://x.com/b",
+                    "https://x.com/a#top",
+                ],
+                &["HTTPS://X.com/a", "https://x.com/a#top", "https://x.com/b"],
+            ),
+        ];
+        for (strategy, input, expect) in cases {
+            let lines = SortableLine::numbered(input);
+            let sorter = Sorter::new(
+                *strategy,
+                &SorterOptions {
+                    unique: true,
+                    ..Default::default()
+                },
+            )?;
+            assert!(
+                matches!(
+                    sorter
+                        .lines_are_sorted(&lines)
+                        .as_ref()
+                        .map_err(|e| e.downcast_ref::<CheckError>()),
+                    Err(Some(CheckError::NotUnique { .. })),
+                ),
+                "{strategy:?} lines are not unique",
+            );
+            let sorted = sorter.sort_lines(lines)?;
+            assert_eq!(
+                sorted.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(),
+                *expect,
+                "{strategy:?}",
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn indent_tree() -> Result<()> {
+        let lines = SortableLine::numbered(&["/b", "/a/b/c", "/a", "/ab", "/a/b", "/a/c", "c"]);
+        let sorter = Sorter::new(
+            Strategy::Path,
+            &SorterOptions {
+                path_order: PathOrder::Tree,
+                indent: true,
+                ..Default::default()
+            },
+        )?;
+        let sorted = sorter.sort_lines(lines)?;
+        assert_eq!(
+            sorted.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(),
+            ["/a", "  /a/b", "    /a/b/c", "  /a/c", "/ab", "/b", "c"],
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_paths() -> Result<()> {
+        let cases: &[(PathType, bool, &[&str], &[&str])] = &[
+            (
+                PathType::Unix,
+                false,
+                &["a/c/../b", "a/b", "../x/../y", "/../z", "a/b/"],
+                &["/../z", "../x/../y", "a/b"],
+            ),
+            (
+                PathType::Unix,
+                true,
+                &["a/c/../b", "a/b", "../x/../y", "/../z", "a/b/"],
+                &["/z", "../y", "a/b"],
+            ),
+            (
+                PathType::Windows,
+                false,
+                &["c:/foo/BAR/", r"C:\foo\baz", r"C:\Foo\bar"],
+                &[r"C:\Foo\bar", r"C:\foo\baz"],
+            ),
+            (
+                PathType::Mixed,
+                false,
+                &[r"x\y", r"a\B", "c/d", "a/b", r"A\b", "c/D", r"x\Y"],
+                &["a/b", r"A\b", "c/D", "c/d", r"x\Y"],
+            ),
+        ];
+        for (path_type, canonicalize, input, expect) in cases {
+            let lines = SortableLine::numbered(input);
+            let sorter = Sorter::new(
+                Strategy::Path,
+                &SorterOptions {
+                    unique: true,
+                    path_type: *path_type,
+                    canonicalize: *canonicalize,
+                    normalize: true,
+                    ..Default::default()
+                },
+            )?;
+            let sorted = sorter.sort_lines(lines.clone())?;
+            assert_eq!(
+                sorted.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(),
+                *expect,
+                "{input:?}",
+            );
+            assert!(sorter.lines_are_sorted(&sorted)?, "{input:?} are sorted");
+        }
+
+        let sorter = Sorter::new(
+            Strategy::Path,
+            &SorterOptions {
+                unique: true,
+                normalize: true,
+                ..Default::default()
+            },
+        )?;
+        let mut lines = SortableLine::numbered(&["a/./b", "a/b", "d/e"]);
+        assert!(
+            matches!(
+                sorter
+                    .lines_are_sorted(&lines)
+                    .as_ref()
+                    .map_err(|e| e.downcast_ref::<CheckError>()),
+                Err(Some(CheckError::NotUnique { .. })),
+            ),
+            "equivalent paths are not unique",
+        );
+
+        lines.remove(1);
+        assert!(
+            !sorter.add_line(
+                &mut lines,
+                SortableLine::from_number_and_str((0, "a/c/../b/"))
+            )?,
+            "an equivalent path is not added with --unique",
+        );
+        assert_eq!(sorter.remove_line(&mut lines, "a//b")?, 1);
+        assert_eq!(
+            lines.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(),
+            ["d/e"],
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn collapse_lines() -> Result<()> {
+        let lines = SortableLine::numbered(&[
+            "10.0.0.128/25",
+            "10.0.1.0/24",
+            "10.0.0.0/25",
+            "10.0.0.64/26",
+            "192.168.0.0/16",
+            "192.168.1.1/32",
+            "2001:db8::/32",
+            "10.0.3.0/24",
+        ]);
+        let sorted = |aggregate, reverse| -> Result<Vec<String>> {
+            let sorter = Sorter::new(
+                Strategy::Network,
+                &SorterOptions {
+                    reverse,
+                    collapse: true,
+                    aggregate,
+                    ..Default::default()
+                },
+            )?;
+            let sorted = sorter.sort_lines(lines.clone())?;
+            assert!(
+                sorter.lines_are_sorted(&sorted)?,
+                "collapsed lines pass the check",
+            );
+            Ok(sorted.into_iter().map(|l| l.line).collect())
+        };
+
+        assert_eq!(
+            sorted(false, false)?,
+            [
+                "10.0.0.0/25",
+                "10.0.0.128/25",
+                "10.0.1.0/24",
+                "10.0.3.0/24",
+                "192.168.0.0/16",
+                "2001:db8::/32",
+            ],
+        );
+        assert_eq!(
+            sorted(true, false)?,
+            [
+                "10.0.0.0/23",
+                "10.0.3.0/24",
+                "192.168.0.0/16",
+                "2001:db8::/32"
+            ],
+        );
+        assert_eq!(
+            sorted(true, true)?,
+            [
+                "2001:db8::/32",
+                "192.168.0.0/16",
+                "10.0.3.0/24",
+                "10.0.0.0/23"
+            ],
+        );
+
+        let sorter = Sorter::new(
+            Strategy::Network,
+            &SorterOptions {
+                collapse: true,
+                ..Default::default()
+            },
+        )?;
+        let res = sorter.lines_are_sorted(&sorter.sort_lines(lines.clone())?);
+        assert!(res.is_ok(), "collapsed lines are sorted: {res:?}");
+        let lines = SortableLine::numbered(&["10.0.0.0/8", "10.1.0.0/16"]);
+        let res = sorter.lines_are_sorted(&lines);
+        assert!(
+            matches!(
+                res.as_ref().map_err(|e| e.downcast_ref::<CheckError>()),
+                Err(Some(CheckError::NotCollapsed { line_number: 2, .. })),
+            ),
+            "contained networks mean the lines are not collapsed: {res:?}",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn collapse_lines_with_host_bits() -> Result<()> {
+        let sorter = |aggregate| {
+            Sorter::new(
+                Strategy::Network,
+                &SorterOptions {
+                    collapse: true,
+                    aggregate,
+                    ..Default::default()
+                },
+            )
+        };
+
+        // The larger network sorts last since its address as written is
+        // larger, but it still contains the smaller network.
+        let lines = SortableLine::numbered(&["10.0.0.0/24", "10.0.0.5/8"]);
+        for aggregate in [false, true] {
+            let sorted = sorter(aggregate)?.sort_lines(lines.clone())?;
+            assert_eq!(
+                sorted.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(),
+                ["10.0.0.5/8"],
+                "collapsed with aggregate = {aggregate}",
+            );
+        }
+        let res = sorter(false)?.lines_are_sorted(&lines);
+        assert!(
+            matches!(
+                res.as_ref().map_err(|e| e.downcast_ref::<CheckError>()),
+                Err(Some(CheckError::NotCollapsed { line_number: 1, .. })),
+            ),
+            "a network contained in a network with host bits set is reported: {res:?}",
+        );
+
+        let sorted = sorter(true)?.sort_lines(SortableLine::numbered(&[
+            "10.0.0.129/25",
+            "10.0.0.0/25",
+            "10.0.1.7/24",
+        ]))?;
+        assert_eq!(
+            sorted.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(),
+            ["10.0.0.0/23"],
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn overlapping_networks() -> Result<()> {
+        let lines = SortableLine::numbered(&[
+            "10.0.0.0/8",
+            "10.1.0.0/16",
+            "10.1.0.0/16",
+            "10.2.0.1/24",
+            "192.168.0.0/24",
+        ]);
+        let overlaps =
+            |lines: &[SortableLine], reverse| -> Result<Vec<(usize, usize, Relationship)>> {
+                let sorter = Sorter::new(
+                    Strategy::Network,
+                    &SorterOptions {
+                        reverse,
+                        no_overlap: true,
+                        ..Default::default()
+                    },
+                )?;
+                match sorter.lines_are_sorted(lines) {
+                    Ok(_) => Ok(vec![]),
+                    Err(e) => match e.downcast::<CheckError>()? {
+                        CheckError::HasOverlappingNetworks { overlaps } => Ok(overlaps
+                            .into_iter()
+                            .map(|o| (o.first_line_number, o.second_line_number, o.relationship))
+                            .collect()),
+                        e => Err(e.into()),
+                    },
+                }
+            };
+
+        assert_eq!(
+            overlaps(&lines, false)?,
+            [
+                (1, 2, Relationship::Subset),
+                (2, 3, Relationship::Duplicate),
+                (1, 4, Relationship::Subset),
+            ],
+        );
+        assert_eq!(
+            overlaps(&lines[1..], false)?,
+            [(2, 3, Relationship::Duplicate)],
+        );
+
+        let reversed =
+            SortableLine::numbered(&["192.168.0.0/24", "10.2.0.1/24", "10.1.0.0/16", "10.0.0.0/8"]);
+        assert_eq!(
+            overlaps(&reversed, true)?,
+            [
+                (2, 4, Relationship::Superset),
+                (3, 4, Relationship::Superset),
+            ],
+        );
+
+        assert_eq!(
+            overlaps(&lines[3..], false)?,
+            [],
+            "no overlaps between disjoint networks",
+        );
+
+        let ranges =
+            SortableLine::numbered(&["10.0.0.0-10.0.0.20", "10.0.0.10-10.0.0.30", "10.0.0.15"]);
+        assert_eq!(
+            overlaps(&ranges, false)?,
+            [(1, 2, Relationship::Overlap), (2, 3, Relationship::Subset)],
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_lines() -> Result<()> {
+        let inputs = vec![
+            SortableLine::numbered(&["bar", "foo", "quux"]),
+            SortableLine::numbered(&["baz", "foo", "zed"]),
+        ];
+
+        let sorter = Sorter::new(Strategy::Text, &SorterOptions::default())?;
+        let merged = sorter
+            .merge_lines(inputs.clone())
+            .collect::<Result<Vec<_>>>()?;
+        let expect = [
+            (1, "bar"),
+            (1, "baz"),
+            (2, "foo"),
+            (2, "foo"),
+            (3, "quux"),
+            (3, "zed"),
+        ]
+        .into_iter()
+        .map(SortableLine::from_number_and_str)
+        .collect::<Vec<_>>();
+        assert_eq!(merged, expect, "got expected merged lines");
+
+        let sorter = Sorter::new(
+            Strategy::Text,
+            &SorterOptions {
+                unique: true,
+                ..Default::default()
+            },
+        )?;
+        let merged = sorter.merge_lines(inputs).collect::<Result<Vec<_>>>()?;
+        let expect = [(1, "bar"), (1, "baz"), (2, "foo"), (3, "quux"), (3, "zed")]
+            .into_iter()
+            .map(SortableLine::from_number_and_str)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            merged, expect,
+            "got expected merged lines with duplicates removed"
+        );
+
+        let sorter = Sorter::new(
+            Strategy::Text,
+            &SorterOptions {
+                reverse: true,
+                ..Default::default()
+            },
+        )?;
+        let inputs = vec![
+            SortableLine::numbered(&["c", "b"]),
+            [(3, "b"), (4, "a")]
+                .into_iter()
+                .map(SortableLine::from_number_and_str)
+                .collect::<Vec<_>>(),
+        ];
+        let merged = sorter.merge_lines(inputs).collect::<Result<Vec<_>>>()?;
+        let expect = SortableLine::numbered(&["c", "b", "b", "a"]);
+        assert_eq!(
+            merged, expect,
+            "equal lines are merged in the order the files were given when reversing"
+        );
+        assert!(
+            sorter.lines_are_sorted(&merged)?,
+            "equal lines next to each other are sorted when reversing",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_equal_datetimes() -> Result<()> {
+        let sorter = Sorter::new(
+            Strategy::DatetimeText,
+            &SorterOptions {
+                reverse: true,
+                ..Default::default()
+            },
+        )?;
+        let lines = SortableLine::numbered(&["2026-01-01 a", "2026-01-02 c", "2026-01-01 b"]);
+        let sorted = sorter.sort_lines(lines)?;
+        let strs =
+            |lines: &[SortableLine]| lines.iter().map(|l| l.line.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            strs(&sorted),
+            ["2026-01-02 c", "2026-01-01 b", "2026-01-01 a"],
+            "lines with the same datetime are sorted as text",
+        );
+        assert!(sorter.lines_are_sorted(&sorted)?);
+        assert_eq!(
+            strs(&sorter.sort_lines(sorted.clone())?),
+            strs(&sorted),
+            "sorting again does not change the order",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_and_remove_lines() -> Result<()> {
+        let mut lines = SortableLine::numbered(&["bar", "baz", "foo"]);
+
+        let sorter = Sorter::new(
+            Strategy::Text,
+            &SorterOptions {
+                unique: true,
+                ..Default::default()
+            },
+        )?;
+        assert!(sorter.add_line(&mut lines, SortableLine::from_number_and_str((0, "bat")))?);
+        assert!(sorter.add_line(&mut lines, SortableLine::from_number_and_str((0, "zed")))?);
+        assert!(
+            !sorter.add_line(&mut lines, SortableLine::from_number_and_str((0, "foo")))?,
+            "a duplicate line is not added with --unique",
+        );
+        assert_eq!(
+            lines,
+            [(1, "bar"), (0, "bat"), (2, "baz"), (3, "foo"), (0, "zed")]
+                .into_iter()
+                .map(SortableLine::from_number_and_str)
+                .collect::<Vec<_>>(),
+        );
+
+        assert_eq!(sorter.remove_line(&mut lines, "baz")?, 1);
+        assert_eq!(sorter.remove_line(&mut lines, "quux")?, 0);
+        assert_eq!(
+            lines,
+            [(1, "bar"), (0, "bat"), (3, "foo"), (0, "zed")]
+                .into_iter()
+                .map(SortableLine::from_number_and_str)
+                .collect::<Vec<_>>(),
+        );
+
+        let sorter = Sorter::new(
+            Strategy::Text,
+            &SorterOptions {
+                reverse: true,
+                ..Default::default()
+            },
+        )?;
+        let mut lines = SortableLine::numbered(&["foo", "baz", "bar"]);
+        assert!(sorter.add_line(&mut lines, SortableLine::from_number_and_str((0, "bat")))?);
+        assert!(
+            sorter.add_line(&mut lines, SortableLine::from_number_and_str((0, "foo")))?,
+            "a duplicate line is added without --unique",
+        );
+        assert_eq!(
+            lines,
+            [(1, "foo"), (0, "foo"), (2, "baz"), (0, "bat"), (3, "bar")]
+                .into_iter()
+                .map(SortableLine::from_number_and_str)
+                .collect::<Vec<_>>(),
+            "lines are inserted in reverse order with --reverse",
+        );
+        assert!(
+            sorter.lines_are_sorted(&lines)?,
+            "lines with a duplicate added are still sorted with --reverse",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_lines() -> Result<()> {
+        let found =
+            |lines: &[&SortableLine]| lines.iter().map(|l| l.line.clone()).collect::<Vec<_>>();
+
+        let sorter = Sorter::new(Strategy::Ip, &SorterOptions::default())?;
+        let lines = SortableLine::numbered(&[
+            "1.2.3.4", "10.0.0.1", "10.0.0.9", "10.1.0.0", "11.0.0.1", "::1",
+        ]);
+        assert_eq!(
+            found(
+                &sorter
+                    .find_equal(&lines, "0::1")?
+                    .iter()
+                    .collect::<Vec<_>>()
+            ),
+            ["::1"]
+        );
+        assert!(sorter.find_equal(&lines, "10.0.0.2")?.is_empty());
+        assert_eq!(
+            found(&sorter.find_within(&lines, "10.0.0.0/8")?),
+            ["10.0.0.1", "10.0.0.9", "10.1.0.0"],
+        );
+
+        let sorter = Sorter::new(
+            Strategy::Network,
+            &SorterOptions {
+                reverse: true,
+                ..Default::default()
+            },
+        )?;
+        let lines = SortableLine::numbered(&[
+            "11.0.0.0/8",
+            "10.5.0.0/16",
+            "10.0.1.0/24",
+            "10.0.0.0/16",
+            "10.0.0.0/8",
+        ]);
+        assert_eq!(
+            found(&sorter.find_within(&lines, "10.0.0.0/16")?),
+            ["10.0.1.0/24", "10.0.0.0/16"],
+            "found networks in reverse order",
+        );
+
+        let sorter = Sorter::new(
+            Strategy::Network,
+            &SorterOptions {
+                canonicalize: true,
+                ..Default::default()
+            },
+        )?;
+        let lines = SortableLine::numbered(&["10.0.0.0/8", "192.0.2.0/24"]);
+        assert_eq!(
+            found(
+                &sorter
+                    .find_equal(&lines, "10.0.0.5/8")?
+                    .iter()
+                    .collect::<Vec<_>>()
+            ),
+            ["10.0.0.0/8"],
+            "the value is canonicalized before looking it up",
+        );
+
+        let sorter = Sorter::new(Strategy::Text, &SorterOptions::default())?;
+        let lines = SortableLine::numbered(&["apple", "banana", "band", "bar", "bat"]);
+        assert_eq!(
+            found(
+                &sorter
+                    .find_with_prefix(&lines, "ban", false)?
+                    .iter()
+                    .collect::<Vec<_>>()
+            ),
+            ["banana", "band"],
+        );
+        assert!(sorter.find_with_prefix(&lines, "c", false)?.is_empty());
+
+        let sorter = Sorter::new(
+            Strategy::Text,
+            &SorterOptions {
+                reverse: true,
+                ..Default::default()
+            },
+        )?;
+        let lines = SortableLine::numbered(&["bat", "bar", "band", "banana", "apple"]);
+        assert_eq!(
+            found(
+                &sorter
+                    .find_with_prefix(&lines, "ban", false)?
+                    .iter()
+                    .collect::<Vec<_>>()
+            ),
+            ["band", "banana"],
+            "found lines with prefix in reverse order",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn detect() {
+        let cases: &[(&str, &[&str], Strategy)] = &[
+            ("empty", &[], Strategy::Text),
+            ("ip", &["1.1.1.1", "::1", "10.0.0.1"], Strategy::Ip),
+            ("network", &["1.1.1.0/24", "::1/128"], Strategy::Network),
+            (
+                "ip and network",
+                &["1.1.1.0/24", "10.0.0.1"],
+                Strategy::Network,
+            ),
+            (
+                "datetime",
+                &["2017-01-12", "1991-01-02", "2017-01-12T14:01:01Z"],
+                Strategy::DatetimeText,
+            ),
+            (
+                "numbered text",
+                &["1. foo", "10. bar", "2.5 baz"],
+                Strategy::NumberedText,
+            ),
+            (
+                "ip text",
+                &["127.0.0.1 localhost", "::1 localhost", "10.0.0.1"],
+                Strategy::IpText,
+            ),
+            ("path", &["/foo/bar", "a/b", r"C:\x"], Strategy::Path),
+            (
+                "url",
+                &["https://example.com/a", "http://[::1]:8080/"],
+                Strategy::Url,
+            ),
+            (
+                "email",
+                &["user@example.com", "Someone <x@bücher.example>"],
+                Strategy::Email,
+            ),
+            (
+                "domain",
+                &["example.com", "*.example.org", "xn--bcher-kva.example."],
+                Strategy::Domain,
+            ),
+            ("numbers", &["1.5", "2.25"], Strategy::NumberedText),
+            ("words", &["foo", "bar"], Strategy::Text),
+            ("text", &["foo", "bar", "/baz"], Strategy::Text),
+        ];
+        for (name, lines, expect) in cases {
+            assert_eq!(Strategy::detect(lines, true), *expect, "{name}");
+        }
+
+        let mut lines = vec!["1.1.1.1"; 2000];
+        lines[1] = "not an ip";
+        assert_eq!(
+            Strategy::detect(&lines, true),
+            Strategy::Ip,
+            "sampling skips lines",
+        );
+        assert_eq!(
+            Strategy::detect(&lines, false),
+            Strategy::IpText,
+            "looking at every line does not skip lines",
+        );
+    }
 }